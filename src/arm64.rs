@@ -0,0 +1,260 @@
+// AArch64 backend: lowers TACKY to assembly for Apple Silicon, as the alternative to the
+// x86-64 backend. Every TACKY variable lives in its own stack slot; each instruction loads
+// its operands into scratch registers, computes, and stores the result back.
+use crate::ast::{BinaryOp, UnaryOp}; // Import the operator types TACKY shares with the AST
+use crate::tacky::{TackyFunction, TackyInstruction, TackyProgram, Val}; // Import the TACKY IR
+use std::collections::HashMap; // Import map type for variable stack slots
+
+// Size in bytes of an int stack slot
+const SLOT_SIZE: usize = 4;
+
+// Largest frame 'sub sp, sp, #bytes' can reserve in one instruction
+const MAX_FRAME_SIZE: usize = 4080;
+
+// Registers that carry the first arguments of a call, in order
+const ARG_REGISTERS: [&str; 8] = ["w0", "w1", "w2", "w3", "w4", "w5", "w6", "w7"];
+
+// Render a whole program as AArch64 assembly
+pub fn gen(program: &TackyProgram) -> Result<String, String> {
+    let mut asm = String::new();
+    for function in &program.functions {
+        asm.push_str(&gen_function(function)?);
+    }
+    Ok(asm)
+}
+
+// Render one function: export its symbol, set up its frame, spill its parameters, and lower its body
+fn gen_function(function: &TackyFunction) -> Result<String, String> {
+    if function.params.len() > ARG_REGISTERS.len() {
+        return Err(format!(
+            "Codegen Error: function '{}' has more than {} parameters, which the arm64 backend does not support yet",
+            function.name,
+            ARG_REGISTERS.len()
+        ));
+    }
+    let slots = allocate_stack(function);
+    // The frame holds one slot per variable, rounded up to keep sp 16-byte aligned
+    let frame_size = (slots.len() * SLOT_SIZE).div_ceil(16) * 16;
+    if frame_size > MAX_FRAME_SIZE {
+        return Err(format!("Codegen Error: function '{}' has too many variables for the arm64 backend", function.name));
+    }
+
+    // Mach-O symbols carry a leading underscore
+    let mut gen = Arm64Gen { out: String::new(), slots, function: &function.name, frame_size };
+    gen.out.push_str(&format!("\t.globl _{}\n\t.p2align 2\n_{}:\n", function.name, function.name));
+    gen.line("stp x29, x30, [sp, #-16]!"); // Save the caller's frame pointer and the return address
+    gen.line("mov x29, sp");
+    if frame_size > 0 {
+        gen.line(&format!("sub sp, sp, #{}", frame_size));
+    }
+    for (param, register) in function.params.iter().zip(ARG_REGISTERS) {
+        gen.store(register, param);
+    }
+    for instruction in &function.body {
+        gen.instruction(instruction)?;
+    }
+    Ok(gen.out)
+}
+
+// Give every variable named in the function its own slot at an offset from sp, parameters first
+fn allocate_stack(function: &TackyFunction) -> HashMap<String, usize> {
+    let mut slots = HashMap::new();
+    let mut add = |name: &String| {
+        let next = slots.len() * SLOT_SIZE;
+        slots.entry(name.clone()).or_insert(next);
+    };
+    function.params.iter().for_each(&mut add);
+    for instruction in &function.body {
+        let vals = match instruction {
+            TackyInstruction::Return(Some(val)) | TackyInstruction::JumpIfZero(val, _) | TackyInstruction::JumpIfNotZero(val, _) => {
+                vec![val]
+            }
+            TackyInstruction::Unary(_, src, dst) | TackyInstruction::Copy(src, dst) => vec![src, dst],
+            TackyInstruction::Binary(_, left, right, dst) => vec![left, right, dst],
+            TackyInstruction::Return(None) | TackyInstruction::Jump(_) | TackyInstruction::Label(_) => vec![],
+        };
+        for val in vals {
+            if let Val::Var(name) = val {
+                add(name);
+            }
+        }
+    }
+    slots
+}
+
+// State for rendering the body of a single function
+struct Arm64Gen<'a> {
+    out: String,                   // Assembly text so far
+    slots: HashMap<String, usize>, // Offset from sp of each variable's slot
+    function: &'a str,             // Name of the function, to keep its labels apart from other functions'
+    frame_size: usize,             // Bytes reserved below the saved registers
+}
+
+impl Arm64Gen<'_> {
+    // Append one indented instruction
+    fn line(&mut self, instruction: &str) {
+        self.out.push('\t');
+        self.out.push_str(instruction);
+        self.out.push('\n');
+    }
+
+    // Assembly name of a TACKY label; an 'L' prefix keeps it out of the Mach-O symbol table
+    fn label(&self, label: &str) -> String {
+        format!("L{}.{}", self.function, label)
+    }
+
+    // Load a value into a 32-bit register
+    fn load(&mut self, val: &Val, register: &str) {
+        match val {
+            Val::Constant(value) if (0..=0xffff).contains(value) => self.line(&format!("mov {}, #{}", register, value)),
+            Val::Constant(value) => {
+                // Build other constants sixteen bits at a time
+                let bits = *value as u32;
+                self.line(&format!("movz {}, #{}", register, bits & 0xffff));
+                self.line(&format!("movk {}, #{}, lsl #16", register, bits >> 16));
+            }
+            Val::Var(name) => self.line(&format!("ldr {}, [sp, #{}]", register, self.slots[name])),
+        }
+    }
+
+    // Store a 32-bit register into a variable's slot
+    fn store(&mut self, register: &str, name: &str) {
+        self.line(&format!("str {}, [sp, #{}]", register, self.slots[name]));
+    }
+
+    // Store the result in w9 to a destination, which TACKY always makes a variable
+    fn store_result(&mut self, dst: &Val) -> Result<(), String> {
+        match dst {
+            Val::Var(name) => {
+                self.store("w9", name);
+                Ok(())
+            }
+            Val::Constant(_) => Err("Codegen Error: TACKY destination is a constant".to_string()),
+        }
+    }
+
+    // Release the frame and return to the caller
+    fn push_return(&mut self) {
+        if self.frame_size > 0 {
+            self.line(&format!("add sp, sp, #{}", self.frame_size));
+        }
+        self.line("ldp x29, x30, [sp], #16");
+        self.line("ret");
+    }
+
+    // Lower one TACKY instruction, using w9-w11 as scratch registers
+    fn instruction(&mut self, instruction: &TackyInstruction) -> Result<(), String> {
+        match instruction {
+            TackyInstruction::Return(val) => {
+                if let Some(val) = val {
+                    self.load(val, "w0"); // The return value goes in w0
+                }
+                self.push_return();
+            }
+            TackyInstruction::Unary(op, src, dst) => {
+                self.load(src, "w9");
+                match op {
+                    UnaryOp::Negate => self.line("neg w9, w9"),
+                    UnaryOp::Complement => self.line("mvn w9, w9"),
+                    UnaryOp::Not => {
+                        self.line("cmp w9, #0");
+                        self.line("cset w9, eq");
+                    }
+                }
+                self.store_result(dst)?;
+            }
+            TackyInstruction::Binary(op, left, right, dst) => {
+                self.load(left, "w9");
+                self.load(right, "w10");
+                self.binary(op)?;
+                self.store_result(dst)?;
+            }
+            TackyInstruction::Copy(src, dst) => {
+                self.load(src, "w9");
+                self.store_result(dst)?;
+            }
+            TackyInstruction::Jump(label) => {
+                let label = self.label(label);
+                self.line(&format!("b {}", label));
+            }
+            TackyInstruction::JumpIfZero(val, label) | TackyInstruction::JumpIfNotZero(val, label) => {
+                self.load(val, "w9");
+                let branch = if matches!(instruction, TackyInstruction::JumpIfZero(..)) { "cbz" } else { "cbnz" };
+                let label = self.label(label);
+                self.line(&format!("{} w9, {}", branch, label));
+            }
+            TackyInstruction::Label(label) => {
+                let label = self.label(label);
+                self.out.push_str(&format!("{}:\n", label));
+            }
+        }
+        Ok(())
+    }
+
+    // Compute 'w9 op w10' into w9
+    fn binary(&mut self, op: &BinaryOp) -> Result<(), String> {
+        let mnemonic = match op {
+            BinaryOp::Add => "add",
+            BinaryOp::Subtract => "sub",
+            BinaryOp::Multiply => "mul",
+            BinaryOp::Divide => "sdiv",
+            BinaryOp::BitAnd => "and",
+            BinaryOp::BitOr => "orr",
+            BinaryOp::BitXor => "eor",
+            BinaryOp::ShiftLeft => "lsl",
+            BinaryOp::ShiftRight => "asr", // 'int' is signed, so the shift keeps the sign
+            BinaryOp::Remainder => {
+                // There is no remainder instruction: 'a % b' is 'a - (a / b) * b'
+                self.line("sdiv w11, w9, w10");
+                self.line("msub w9, w11, w10, w9");
+                return Ok(());
+            }
+            BinaryOp::Less
+            | BinaryOp::LessEqual
+            | BinaryOp::Greater
+            | BinaryOp::GreaterEqual
+            | BinaryOp::Equal
+            | BinaryOp::NotEqual => {
+                let cond = match op {
+                    BinaryOp::Less => "lt",
+                    BinaryOp::LessEqual => "le",
+                    BinaryOp::Greater => "gt",
+                    BinaryOp::GreaterEqual => "ge",
+                    BinaryOp::Equal => "eq",
+                    _ => "ne",
+                };
+                self.line("cmp w9, w10");
+                self.line(&format!("cset w9, {}", cond));
+                return Ok(());
+            }
+            // TACKY lowers these to jumps so the right operand can be skipped
+            BinaryOp::And | BinaryOp::Or => {
+                return Err(format!("Codegen Error: '{}' should have been lowered to jumps", op.symbol()));
+            }
+        };
+        self.line(&format!("{} w9, w9, w10", mnemonic));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+    use crate::tacky::emit_tacky;
+
+    // Lex, parse, and lower a source through TACKY to AArch64 assembly
+    fn gen_source(source: &str) -> String {
+        gen(&emit_tacky(&parse(tokenize(source).unwrap()).unwrap()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn return_constant_moves_into_w0() {
+        let asm = gen_source("int main(void) { return 7; }");
+        assert!(asm.contains("\tmov w0, #7\n"));
+        assert!(asm.contains("\t.globl _main\n"));
+        assert!(asm.contains("\n_main:\n"));
+        assert!(asm.contains("\tret\n"));
+    }
+}
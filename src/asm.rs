@@ -0,0 +1,945 @@
+// Assembly backend: lowers the AST to a list of x86-64 instructions, then renders
+// them as AT&T-syntax assembly text
+use crate::ast::{BinaryOp, BlockItem, Declaration, Expr, ForInit, Function, Program, Statement, Type, UnaryOp}; // Import the AST node types
+use crate::typecheck::value_type; // Import the type of a checked expression, for 'sizeof'
+use std::collections::HashMap; // Import map type for variable stack slots
+
+/// Represents a single line of assembly output.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Global(String),           // '.globl name' directive exporting a symbol
+    Label(String),            // 'name:'
+    Jmp(String),              // 'jmp label'
+    JmpCC(CondCode, String),  // 'jCC label', jumps when the flags match the condition
+    Mov(Operand, Operand),    // 'movl src, dst'
+    MovQ(Reg, Reg),           // 'movq src, dst', copies a full 64-bit register
+    AllocateStack(i32),       // 'subq $bytes, %rsp', reserves the stack frame
+    DeallocateStack(i32),     // 'addq $bytes, %rsp', releases stack reserved for a call
+    Neg(Operand),             // 'negl operand', two's complement negation
+    Not(Operand),             // 'notl operand', bitwise complement
+    Cmp(Operand, Operand),    // 'cmpl a, b', sets flags from b - a
+    SetCC(CondCode, Reg),     // 'setCC reg', writes 1 or 0 to the register's low byte
+    Binary(AluOp, Operand, Operand), // 'op src, dst', storing 'dst op src' in dst
+    Cdq,                      // 'cdq', sign-extends %eax into %edx:%eax
+    Idiv(Operand),            // 'idivl divisor', quotient in %eax and remainder in %edx
+    Push(Reg),                // 'pushq reg', saves the full 64-bit register
+    Pop(Reg),                 // 'popq reg'
+    Call(String),             // 'call name', with the result left in %eax
+    Ret,                      // 'ret'
+}
+
+/// Represents the two-operand arithmetic and bitwise instructions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AluOp {
+    Add,  // 'addl'
+    Sub,  // 'subl'
+    Imul, // 'imull'
+    And,  // 'andl'
+    Or,   // 'orl'
+    Xor,  // 'xorl'
+    Sal,  // 'sall', whose count must be an immediate or %cl
+    Sar,  // 'sarl', which keeps the sign since 'int' is signed
+}
+
+/// Represents the flag conditions tested by conditional instructions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CondCode {
+    E,  // Equal
+    NE, // Not equal
+    L,  // Less, as signed integers
+    LE, // Less or equal
+    G,  // Greater
+    GE, // Greater or equal
+}
+
+/// Represents an instruction operand.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operand {
+    Imm(i32),      // Immediate value like '$5'
+    Register(Reg), // Hardware register
+    Stack(i32),    // Stack slot at an offset from %rbp, like '-4(%rbp)'
+}
+
+/// Represents the hardware registers the backend uses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reg {
+    Eax, // Holds return values and the left operand of arithmetic
+    Ecx, // Holds the right operand of arithmetic
+    Edx, // Holds the upper half of the dividend and the remainder
+    Edi, // First argument
+    Esi, // Second argument
+    R8d, // Fifth argument
+    R9d, // Sixth argument
+    Rbp, // Frame pointer; locals live at negative offsets from it
+    Rsp, // Stack pointer
+}
+
+// Lower a whole program to instructions
+pub fn gen(program: &Program) -> Result<Vec<Instruction>, String> {
+    let mut instructions = Vec::new();
+    let mut labels = LabelGen { counter: 0 }; // Shared so labels stay unique across the whole file
+    for function in &program.functions {
+        instructions.extend(gen_function(function, &mut labels)?);
+    }
+    Ok(instructions)
+}
+
+// Source of local labels for control flow that never collide within one assembly file
+struct LabelGen {
+    counter: usize, // Number of labels handed out so far
+}
+
+impl LabelGen {
+    // Make a new '.L' label, like '.Lend_3'; the '.L' prefix keeps it out of the symbol table
+    fn fresh(&mut self, prefix: &str) -> String {
+        let label = format!(".L{}_{}", prefix, self.counter);
+        self.counter += 1;
+        label
+    }
+}
+
+// Size in bytes of an int stack slot
+const SLOT_SIZE: i32 = 4;
+
+// The registers that pass the first six integer arguments in the System V calling convention
+const ARG_REGISTERS: [Reg; 6] = [Reg::Edi, Reg::Esi, Reg::Edx, Reg::Ecx, Reg::R8d, Reg::R9d];
+
+// Lower one function: export its symbol, set up its stack frame, spill its parameters, and
+// lower its body
+fn gen_function(function: &Function, labels: &mut LabelGen) -> Result<Vec<Instruction>, String> {
+    if function.params.len() > ARG_REGISTERS.len() {
+        return Err(format!(
+            "Codegen Error: function '{}' has more than {} parameters, which the x86-64 backend does not support yet",
+            function.name,
+            ARG_REGISTERS.len()
+        ));
+    }
+    let slots = allocate_stack(function);
+    // The frame holds one slot per local, rounded up to keep %rsp 16-byte aligned
+    let frame_size = (slots.len() as i32 * SLOT_SIZE + 15) / 16 * 16;
+    let mut gen = FunctionGen {
+        instructions: vec![
+            Instruction::Global(function.name.clone()),
+            Instruction::Label(function.name.clone()),
+            Instruction::Push(Reg::Rbp), // Save the caller's frame pointer
+            Instruction::MovQ(Reg::Rsp, Reg::Rbp), // Start this function's frame
+            Instruction::AllocateStack(frame_size),
+        ],
+        slots,
+        function: function.name.clone(),
+        labels,
+        loops: Vec::new(),
+        pushed: 0,
+    };
+    // Each parameter arrives in its register and lives in its stack slot from then on
+    for ((_, param), register) in function.params.iter().zip(ARG_REGISTERS) {
+        let slot = gen.slot(param)?;
+        gen.instructions.push(Instruction::Mov(Operand::Register(register), slot));
+    }
+    for item in &function.body {
+        gen.gen_block_item(item)?;
+    }
+    // Falling off the end of a function returns 0, as 'main' is required to do
+    gen.instructions.push(Instruction::Mov(Operand::Imm(0), Operand::Register(Reg::Eax)));
+    gen.push_return();
+    Ok(gen.instructions)
+}
+
+// Assign every parameter and every local variable declared anywhere in the body its own
+// stack slot, at increasingly negative offsets from %rbp
+fn allocate_stack(function: &Function) -> HashMap<String, i32> {
+    let mut slots = HashMap::new();
+    for (_, param) in &function.params {
+        let next = -(slots.len() as i32 + 1) * SLOT_SIZE;
+        slots.insert(param.clone(), next);
+    }
+    for item in &function.body {
+        collect_block_item(item, &mut slots);
+    }
+    slots
+}
+
+// Record the declarations inside one block item
+fn collect_block_item(item: &BlockItem, slots: &mut HashMap<String, i32>) {
+    match item {
+        BlockItem::Declaration(declaration) => collect_declaration(declaration, slots),
+        BlockItem::Statement(statement) => collect_statement(statement, slots),
+    }
+}
+
+// Record the declarations nested inside a statement
+fn collect_statement(statement: &Statement, slots: &mut HashMap<String, i32>) {
+    match statement {
+        Statement::If { then, else_branch, .. } => {
+            collect_statement(then, slots);
+            if let Some(else_branch) = else_branch {
+                collect_statement(else_branch, slots);
+            }
+        }
+        Statement::While { body, .. } | Statement::DoWhile { body, .. } => collect_statement(body, slots),
+        Statement::For { init, body, .. } => {
+            if let Some(ForInit::Declaration(declaration)) = init {
+                collect_declaration(declaration, slots);
+            }
+            collect_statement(body, slots);
+        }
+        Statement::Block(items) => {
+            for item in items {
+                collect_block_item(item, slots);
+            }
+        }
+        Statement::Switch { body: statement, .. }
+        | Statement::Case(_, statement)
+        | Statement::Default(statement)
+        | Statement::Label(_, statement) => collect_statement(statement, slots),
+        Statement::Return(_) | Statement::Expression(_) | Statement::Break | Statement::Continue | Statement::Goto(_) => {}
+    }
+}
+
+// Give a declared variable the next free slot, unless it already has one
+fn collect_declaration(declaration: &Declaration, slots: &mut HashMap<String, i32>) {
+    let next = -(slots.len() as i32 + 1) * SLOT_SIZE;
+    slots.entry(declaration.name.clone()).or_insert(next);
+}
+
+// State for lowering the body of a single function
+struct FunctionGen<'a> {
+    instructions: Vec<Instruction>, // Instructions emitted so far
+    slots: HashMap<String, i32>, // Stack offset of each local variable
+    function: String, // Name of the function being lowered
+    labels: &'a mut LabelGen, // Label generator shared by every function in the file
+    loops: Vec<(String, String)>, // Continue and break labels of the enclosing loops, innermost last
+    pushed: usize, // Temporaries pushed and not yet popped, which move %rsp off its 16-byte alignment when odd
+}
+
+impl FunctionGen<'_> {
+    // Tear down the stack frame and return to the caller
+    fn push_return(&mut self) {
+        self.instructions.push(Instruction::MovQ(Reg::Rbp, Reg::Rsp)); // Release the frame
+        self.instructions.push(Instruction::Pop(Reg::Rbp)); // Restore the caller's frame pointer
+        self.instructions.push(Instruction::Ret);
+    }
+
+    // Assembly name of a label written in the source; labels are local to their function,
+    // so the function name keeps equal labels in different functions apart
+    fn user_label(&self, label: &str) -> String {
+        format!(".L{}.{}", self.function, label)
+    }
+
+    // Find the stack slot holding a variable
+    fn slot(&self, name: &str) -> Result<Operand, String> {
+        match self.slots.get(name) {
+            Some(offset) => Ok(Operand::Stack(*offset)),
+            None => Err(format!("Codegen Error: undeclared variable '{}'", name)),
+        }
+    }
+
+    // Lower a declaration or statement
+    fn gen_block_item(&mut self, item: &BlockItem) -> Result<(), String> {
+        match item {
+            BlockItem::Declaration(declaration) => self.gen_declaration(declaration),
+            BlockItem::Statement(statement) => self.gen_statement(statement),
+        }
+    }
+
+    // Lower a declaration; only an initializer produces code
+    fn gen_declaration(&mut self, declaration: &Declaration) -> Result<(), String> {
+        if let Some(init) = &declaration.init {
+            self.gen_expr(init)?;
+            let slot = self.slot(&declaration.name)?;
+            self.instructions.push(Instruction::Mov(Operand::Register(Reg::Eax), slot)); // Store the initial value
+        }
+        Ok(())
+    }
+
+    // Lower a statement
+    fn gen_statement(&mut self, statement: &Statement) -> Result<(), String> {
+        match statement {
+            Statement::Return(expr) => {
+                if let Some(expr) = expr {
+                    self.gen_expr(expr)?; // The return value is left in %eax
+                }
+                self.push_return();
+            }
+            Statement::Expression(expr) => self.gen_expr(expr)?, // Evaluate for side effects only
+            Statement::Block(items) => {
+                for item in items {
+                    self.gen_block_item(item)?;
+                }
+            }
+            Statement::If { cond, then, else_branch } => {
+                let end_label = self.labels.fresh("if_end");
+                // Without an else branch a false condition skips straight to the end
+                let false_label = match else_branch {
+                    Some(_) => self.labels.fresh("if_else"),
+                    None => end_label.clone(),
+                };
+                self.gen_expr(cond)?;
+                self.instructions.push(Instruction::Cmp(Operand::Imm(0), Operand::Register(Reg::Eax)));
+                self.instructions.push(Instruction::JmpCC(CondCode::E, false_label.clone()));
+                self.gen_statement(then)?;
+                if let Some(else_branch) = else_branch {
+                    self.instructions.push(Instruction::Jmp(end_label.clone()));
+                    self.instructions.push(Instruction::Label(false_label));
+                    self.gen_statement(else_branch)?;
+                }
+                self.instructions.push(Instruction::Label(end_label));
+            }
+            Statement::While { cond, body } => {
+                // The condition is re-tested on every iteration, so 'continue' jumps back to it
+                let start_label = self.labels.fresh("while_start");
+                let end_label = self.labels.fresh("while_end");
+                self.instructions.push(Instruction::Label(start_label.clone()));
+                self.gen_condition_exit(cond, &end_label)?;
+                self.gen_loop_body(body, &start_label, &end_label)?;
+                self.instructions.push(Instruction::Jmp(start_label));
+                self.instructions.push(Instruction::Label(end_label));
+            }
+            Statement::DoWhile { body, cond } => {
+                // The body runs once before the first test; 'continue' jumps to the test
+                let start_label = self.labels.fresh("do_start");
+                let continue_label = self.labels.fresh("do_continue");
+                let end_label = self.labels.fresh("do_end");
+                self.instructions.push(Instruction::Label(start_label.clone()));
+                self.gen_loop_body(body, &continue_label, &end_label)?;
+                self.instructions.push(Instruction::Label(continue_label));
+                self.gen_expr(cond)?;
+                self.instructions.push(Instruction::Cmp(Operand::Imm(0), Operand::Register(Reg::Eax)));
+                self.instructions.push(Instruction::JmpCC(CondCode::NE, start_label));
+                self.instructions.push(Instruction::Label(end_label));
+            }
+            Statement::For { init, cond, post, body } => {
+                match init {
+                    Some(ForInit::Declaration(declaration)) => self.gen_declaration(declaration)?,
+                    Some(ForInit::Expression(expr)) => self.gen_expr(expr)?,
+                    None => {}
+                }
+                let start_label = self.labels.fresh("for_start");
+                let continue_label = self.labels.fresh("for_continue");
+                let end_label = self.labels.fresh("for_end");
+                self.instructions.push(Instruction::Label(start_label.clone()));
+                // A missing condition loops forever
+                if let Some(cond) = cond {
+                    self.gen_condition_exit(cond, &end_label)?;
+                }
+                self.gen_loop_body(body, &continue_label, &end_label)?;
+                self.instructions.push(Instruction::Label(continue_label));
+                if let Some(post) = post {
+                    self.gen_expr(post)?;
+                }
+                self.instructions.push(Instruction::Jmp(start_label));
+                self.instructions.push(Instruction::Label(end_label));
+            }
+            Statement::Switch { .. } | Statement::Case(..) | Statement::Default(_) => {
+                return Err("Codegen Error: 'switch' statements are not supported yet".to_string())
+            }
+            Statement::Goto(label) => {
+                let label = self.user_label(label);
+                self.instructions.push(Instruction::Jmp(label));
+            }
+            Statement::Label(label, statement) => {
+                let label = self.user_label(label);
+                self.instructions.push(Instruction::Label(label));
+                self.gen_statement(statement)?;
+            }
+            Statement::Break => match self.loops.last() {
+                Some((_, break_label)) => self.instructions.push(Instruction::Jmp(break_label.clone())),
+                None => return Err("Codegen Error: 'break' statement not in a loop".to_string()),
+            },
+            Statement::Continue => match self.loops.last() {
+                Some((continue_label, _)) => self.instructions.push(Instruction::Jmp(continue_label.clone())),
+                None => return Err("Codegen Error: 'continue' statement not in a loop".to_string()),
+            },
+        }
+        Ok(())
+    }
+
+    // Evaluate a loop condition and leave the loop through `exit_label` when it is zero
+    fn gen_condition_exit(&mut self, cond: &Expr, exit_label: &str) -> Result<(), String> {
+        self.gen_expr(cond)?;
+        self.instructions.push(Instruction::Cmp(Operand::Imm(0), Operand::Register(Reg::Eax)));
+        self.instructions.push(Instruction::JmpCC(CondCode::E, exit_label.to_string()));
+        Ok(())
+    }
+
+    // Lower a loop body with 'break' and 'continue' bound to the given labels
+    fn gen_loop_body(&mut self, body: &Statement, continue_label: &str, break_label: &str) -> Result<(), String> {
+        self.loops.push((continue_label.to_string(), break_label.to_string()));
+        let result = self.gen_statement(body);
+        self.loops.pop();
+        result
+    }
+
+    // Save a register's value on the stack as a temporary
+    fn push(&mut self, reg: Reg) {
+        self.instructions.push(Instruction::Push(reg));
+        self.pushed += 1;
+    }
+
+    // Take the most recent temporary off the stack
+    fn pop(&mut self, reg: Reg) {
+        self.instructions.push(Instruction::Pop(reg));
+        self.pushed -= 1;
+    }
+
+    // Lower a call, leaving the result in %eax. Each argument is evaluated left to right and
+    // pushed, since evaluating a later one may overwrite the argument registers, and all of
+    // them are popped into their registers just before the call.
+    fn gen_call(&mut self, name: &str, args: &[Expr]) -> Result<(), String> {
+        if args.len() > ARG_REGISTERS.len() {
+            return Err(format!(
+                "Codegen Error: call to '{}' passes more than {} arguments, which the x86-64 backend does not support yet",
+                name,
+                ARG_REGISTERS.len()
+            ));
+        }
+        for arg in args {
+            self.gen_expr(arg)?;
+            self.push(Reg::Eax);
+        }
+        for register in ARG_REGISTERS[..args.len()].iter().rev() {
+            self.pop(register.clone());
+        }
+        // The callee expects %rsp 16-byte aligned, which it is between temporaries in pairs
+        let padding = if self.pushed % 2 == 1 { 8 } else { 0 };
+        if padding > 0 {
+            self.instructions.push(Instruction::AllocateStack(padding));
+        }
+        self.instructions.push(Instruction::Call(name.to_string()));
+        if padding > 0 {
+            self.instructions.push(Instruction::DeallocateStack(padding));
+        }
+        Ok(())
+    }
+
+    // Lower '++' or '--', leaving the old value in %eax for the postfix forms and the
+    // new one for the prefix forms
+    fn gen_step(&mut self, operand: &Expr, op: AluOp, postfix: bool) -> Result<(), String> {
+        let Expr::Var(name) = operand else {
+            return Err("Codegen Error: invalid operand of '++' or '--'".to_string());
+        };
+        let slot = self.slot(name)?;
+        self.instructions.push(Instruction::Mov(slot.clone(), Operand::Register(Reg::Eax)));
+        if postfix {
+            self.instructions.push(Instruction::Binary(op, Operand::Imm(1), slot)); // Update memory, keeping the old value
+        } else {
+            self.instructions.push(Instruction::Binary(op, Operand::Imm(1), Operand::Register(Reg::Eax)));
+            self.instructions.push(Instruction::Mov(Operand::Register(Reg::Eax), slot));
+        }
+        Ok(())
+    }
+
+    // Lower an expression so that its value ends up in %eax
+    fn gen_expr(&mut self, expr: &Expr) -> Result<(), String> {
+        let eax = Operand::Register(Reg::Eax);
+        match expr {
+            Expr::Constant(value) => self.instructions.push(Instruction::Mov(Operand::Imm(*value), eax)),
+            Expr::Unary(op, operand) => {
+                self.gen_expr(operand)?; // Evaluate the operand into %eax first
+                match op {
+                    UnaryOp::Negate => self.instructions.push(Instruction::Neg(eax)),
+                    UnaryOp::Complement => self.instructions.push(Instruction::Not(eax)),
+                    UnaryOp::Not => {
+                        // %eax becomes 1 if it was zero and 0 otherwise; mov leaves the flags alone
+                        self.instructions.push(Instruction::Cmp(Operand::Imm(0), eax.clone()));
+                        self.instructions.push(Instruction::Mov(Operand::Imm(0), eax));
+                        self.instructions.push(Instruction::SetCC(CondCode::E, Reg::Eax));
+                    }
+                }
+            }
+            Expr::Binary(op @ (BinaryOp::And | BinaryOp::Or), left, right) => {
+                // Stop at the first operand that decides the result: zero for '&&', non-zero for '||'
+                let is_and = matches!(op, BinaryOp::And);
+                let short_label = self.labels.fresh(if is_and { "and_false" } else { "or_true" });
+                let end_label = self.labels.fresh(if is_and { "and_end" } else { "or_end" });
+                let short_cond = if is_and { CondCode::E } else { CondCode::NE };
+                for operand in [left, right] {
+                    self.gen_expr(operand)?;
+                    self.instructions.push(Instruction::Cmp(Operand::Imm(0), eax.clone()));
+                    self.instructions.push(Instruction::JmpCC(short_cond.clone(), short_label.clone()));
+                }
+                self.instructions.push(Instruction::Mov(Operand::Imm(if is_and { 1 } else { 0 }), eax.clone()));
+                self.instructions.push(Instruction::Jmp(end_label.clone()));
+                self.instructions.push(Instruction::Label(short_label));
+                self.instructions.push(Instruction::Mov(Operand::Imm(if is_and { 0 } else { 1 }), eax));
+                self.instructions.push(Instruction::Label(end_label));
+            }
+            Expr::Binary(op, left, right) => {
+                // Evaluate the left operand and save it on the stack while the right one is computed
+                self.gen_expr(left)?;
+                self.push(Reg::Eax);
+                self.gen_expr(right)?;
+                let ecx = Operand::Register(Reg::Ecx);
+                self.instructions.push(Instruction::Mov(eax.clone(), ecx.clone())); // Right operand in %ecx
+                self.pop(Reg::Eax); // Left operand back in %eax
+                match op {
+                    BinaryOp::Add => self.instructions.push(Instruction::Binary(AluOp::Add, ecx, eax)),
+                    BinaryOp::Subtract => self.instructions.push(Instruction::Binary(AluOp::Sub, ecx, eax)),
+                    BinaryOp::Multiply => self.instructions.push(Instruction::Binary(AluOp::Imul, ecx, eax)),
+                    BinaryOp::BitAnd => self.instructions.push(Instruction::Binary(AluOp::And, ecx, eax)),
+                    BinaryOp::BitOr => self.instructions.push(Instruction::Binary(AluOp::Or, ecx, eax)),
+                    BinaryOp::BitXor => self.instructions.push(Instruction::Binary(AluOp::Xor, ecx, eax)),
+                    BinaryOp::ShiftLeft => self.instructions.push(Instruction::Binary(AluOp::Sal, ecx, eax)), // Count in %cl
+                    BinaryOp::ShiftRight => self.instructions.push(Instruction::Binary(AluOp::Sar, ecx, eax)),
+                    BinaryOp::Less
+                    | BinaryOp::LessEqual
+                    | BinaryOp::Greater
+                    | BinaryOp::GreaterEqual
+                    | BinaryOp::Equal
+                    | BinaryOp::NotEqual => {
+                        let cond = match op {
+                            BinaryOp::Less => CondCode::L,
+                            BinaryOp::LessEqual => CondCode::LE,
+                            BinaryOp::Greater => CondCode::G,
+                            BinaryOp::GreaterEqual => CondCode::GE,
+                            BinaryOp::Equal => CondCode::E,
+                            _ => CondCode::NE,
+                        };
+                        // Compare left with right, then turn the flags into 1 or 0; mov leaves the flags alone
+                        self.instructions.push(Instruction::Cmp(ecx, eax.clone()));
+                        self.instructions.push(Instruction::Mov(Operand::Imm(0), eax));
+                        self.instructions.push(Instruction::SetCC(cond, Reg::Eax));
+                    }
+                    BinaryOp::Divide | BinaryOp::Remainder => {
+                        // idivl divides %edx:%eax, so the dividend has to be sign-extended first
+                        self.instructions.push(Instruction::Cdq);
+                        self.instructions.push(Instruction::Idiv(ecx));
+                        if matches!(op, BinaryOp::Remainder) {
+                            self.instructions.push(Instruction::Mov(Operand::Register(Reg::Edx), eax)); // The remainder is in %edx
+                        }
+                    }
+                    // Lowered to jumps above so the right operand can be skipped
+                    BinaryOp::And | BinaryOp::Or => return Err(format!("Codegen Error: unsupported binary operator {:?}", op)),
+                }
+            }
+            Expr::Var(name) => {
+                let slot = self.slot(name)?;
+                self.instructions.push(Instruction::Mov(slot, eax)); // Load the variable
+            }
+            Expr::Assign(target, value) => {
+                let Expr::Var(name) = target.as_ref() else {
+                    return Err("Codegen Error: invalid assignment target".to_string());
+                };
+                self.gen_expr(value)?;
+                let slot = self.slot(name)?;
+                self.instructions.push(Instruction::Mov(eax, slot)); // Store; the value stays in %eax
+            }
+            // The operand is never evaluated, and type checking has ruled out 'void'
+            Expr::SizeOf(operand) => self.instructions.push(Instruction::Mov(Operand::Imm(value_type(operand).size().unwrap()), eax)),
+            Expr::SizeOfType(ty) => match ty.size() {
+                Some(size) => self.instructions.push(Instruction::Mov(Operand::Imm(size), eax)),
+                None => return Err(format!("Codegen Error: type '{}' has no size", ty)),
+            },
+            Expr::FloatConstant(_) | Expr::Cast(Type::Double, _) => {
+                return Err("Codegen Error: 'double' values are not supported yet".to_string());
+            }
+            // Without 'double' values, a cast to 'int' converts nothing, and one to 'void' only discards
+            Expr::Cast(_, operand) => self.gen_expr(operand)?,
+            Expr::Comma(left, right) => {
+                self.gen_expr(left)?; // Evaluate for side effects only; the right operand overwrites %eax
+                self.gen_expr(right)?;
+            }
+            Expr::PrefixInc(operand) => self.gen_step(operand, AluOp::Add, false)?,
+            Expr::PrefixDec(operand) => self.gen_step(operand, AluOp::Sub, false)?,
+            Expr::PostfixInc(operand) => self.gen_step(operand, AluOp::Add, true)?,
+            Expr::PostfixDec(operand) => self.gen_step(operand, AluOp::Sub, true)?,
+            Expr::Call { name, args } => self.gen_call(name, args)?,
+            Expr::Subscript { .. } => return Err("Codegen Error: array subscripts are not supported yet".to_string()),
+            Expr::Deref(_) | Expr::AddrOf(_) => return Err("Codegen Error: pointers are not supported yet".to_string()),
+            Expr::Member { .. } | Expr::PtrMember { .. } => {
+                return Err("Codegen Error: struct members are not supported yet".to_string())
+            }
+            Expr::Conditional { cond, then, else_ } => {
+                let else_label = self.labels.fresh("cond_else");
+                let end_label = self.labels.fresh("cond_end");
+                self.gen_expr(cond)?;
+                self.instructions.push(Instruction::Cmp(Operand::Imm(0), eax));
+                self.instructions.push(Instruction::JmpCC(CondCode::E, else_label.clone()));
+                self.gen_expr(then)?; // Both branches leave their value in %eax
+                self.instructions.push(Instruction::Jmp(end_label.clone()));
+                self.instructions.push(Instruction::Label(else_label));
+                self.gen_expr(else_)?;
+                self.instructions.push(Instruction::Label(end_label));
+            }
+        }
+        Ok(())
+    }
+}
+
+// Render instructions as assembly text
+pub fn emit(instructions: &[Instruction]) -> String {
+    let mut asm = String::new();
+    for instruction in instructions {
+        let line = match instruction {
+            Instruction::Global(name) => format!("\t.globl {}", name),
+            Instruction::Label(name) => format!("{}:", name),
+            Instruction::Jmp(label) => format!("\tjmp {}", label),
+            Instruction::JmpCC(cond, label) => format!("\tj{} {}", emit_cond(cond), label),
+            Instruction::Mov(src, dst) => format!("\tmovl {}, {}", emit_operand(src), emit_operand(dst)),
+            Instruction::MovQ(src, dst) => format!("\tmovq {}, {}", emit_quad_reg(src), emit_quad_reg(dst)),
+            Instruction::AllocateStack(bytes) => format!("\tsubq ${}, %rsp", bytes),
+            Instruction::DeallocateStack(bytes) => format!("\taddq ${}, %rsp", bytes),
+            Instruction::Neg(operand) => format!("\tnegl {}", emit_operand(operand)),
+            Instruction::Not(operand) => format!("\tnotl {}", emit_operand(operand)),
+            Instruction::Cmp(a, b) => format!("\tcmpl {}, {}", emit_operand(a), emit_operand(b)),
+            Instruction::SetCC(cond, reg) => format!("\tset{} {}", emit_cond(cond), emit_byte_reg(reg)),
+            // A shift count held in a register is always named by its low byte
+            Instruction::Binary(op @ (AluOp::Sal | AluOp::Sar), Operand::Register(reg), dst) => {
+                format!("\t{} {}, {}", emit_alu_op(op), emit_byte_reg(reg), emit_operand(dst))
+            }
+            Instruction::Binary(op, src, dst) => format!("\t{} {}, {}", emit_alu_op(op), emit_operand(src), emit_operand(dst)),
+            Instruction::Cdq => "\tcdq".to_string(),
+            Instruction::Idiv(operand) => format!("\tidivl {}", emit_operand(operand)),
+            Instruction::Push(reg) => format!("\tpushq {}", emit_quad_reg(reg)),
+            Instruction::Pop(reg) => format!("\tpopq {}", emit_quad_reg(reg)),
+            Instruction::Call(name) => format!("\tcall {}", name),
+            Instruction::Ret => "\tret".to_string(),
+        };
+        asm.push_str(&line);
+        asm.push('\n');
+    }
+    // Mark the stack as non-executable so the linker doesn't warn on Linux
+    asm.push_str("\t.section .note.GNU-stack,\"\",@progbits\n");
+    asm
+}
+
+// Render the suffix that names a condition code in 'setCC'
+fn emit_cond(cond: &CondCode) -> &'static str {
+    match cond {
+        CondCode::E => "e",
+        CondCode::NE => "ne",
+        CondCode::L => "l",
+        CondCode::LE => "le",
+        CondCode::G => "g",
+        CondCode::GE => "ge",
+    }
+}
+
+// Render the mnemonic of an arithmetic instruction
+fn emit_alu_op(op: &AluOp) -> &'static str {
+    match op {
+        AluOp::Add => "addl",
+        AluOp::Sub => "subl",
+        AluOp::Imul => "imull",
+        AluOp::And => "andl",
+        AluOp::Or => "orl",
+        AluOp::Xor => "xorl",
+        AluOp::Sal => "sall",
+        AluOp::Sar => "sarl",
+    }
+}
+
+// Render the low-byte alias of a register, as written by 'setCC'
+fn emit_byte_reg(reg: &Reg) -> &'static str {
+    match reg {
+        Reg::Eax => "%al",
+        Reg::Ecx => "%cl",
+        Reg::Edx => "%dl",
+        Reg::Edi => "%dil",
+        Reg::Esi => "%sil",
+        Reg::R8d => "%r8b",
+        Reg::R9d => "%r9b",
+        Reg::Rbp => "%bpl",
+        Reg::Rsp => "%spl",
+    }
+}
+
+// Render the 32-bit name of a register
+fn emit_reg(reg: &Reg) -> &'static str {
+    match reg {
+        Reg::Eax => "%eax",
+        Reg::Ecx => "%ecx",
+        Reg::Edx => "%edx",
+        Reg::Edi => "%edi",
+        Reg::Esi => "%esi",
+        Reg::R8d => "%r8d",
+        Reg::R9d => "%r9d",
+        Reg::Rbp => "%ebp",
+        Reg::Rsp => "%esp",
+    }
+}
+
+// Render the 64-bit name of a register, as used by push and pop
+fn emit_quad_reg(reg: &Reg) -> &'static str {
+    match reg {
+        Reg::Eax => "%rax",
+        Reg::Ecx => "%rcx",
+        Reg::Edx => "%rdx",
+        Reg::Edi => "%rdi",
+        Reg::Esi => "%rsi",
+        Reg::R8d => "%r8",
+        Reg::R9d => "%r9",
+        Reg::Rbp => "%rbp",
+        Reg::Rsp => "%rsp",
+    }
+}
+
+// Render a single operand in AT&T syntax
+fn emit_operand(operand: &Operand) -> String {
+    match operand {
+        Operand::Imm(value) => format!("${}", value),
+        Operand::Register(reg) => emit_reg(reg).to_string(),
+        Operand::Stack(offset) => format!("{}(%rbp)", offset),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    // Lex, parse, and lower a source to instructions
+    fn gen_source(source: &str) -> Vec<Instruction> {
+        gen(&parse(tokenize(source).unwrap()).unwrap()).unwrap()
+    }
+
+    // Lower a 'main' with the given body and return the instructions between the prologue
+    // and the first return
+    fn gen_body(body: &str) -> Vec<Instruction> {
+        let instructions = gen_source(&format!("int main(void) {{ {} }}", body));
+        let start = instructions.iter().position(|instruction| matches!(instruction, Instruction::AllocateStack(_))).unwrap() + 1;
+        let end = instructions.iter().position(|instruction| *instruction == Instruction::MovQ(Reg::Rbp, Reg::Rsp)).unwrap();
+        instructions[start..end].to_vec()
+    }
+
+    fn eax() -> Operand {
+        Operand::Register(Reg::Eax)
+    }
+
+    fn ecx() -> Operand {
+        Operand::Register(Reg::Ecx)
+    }
+
+    #[test]
+    fn return_constant_is_a_move_into_eax() {
+        assert_eq!(
+            gen_source("int main(void) { return 5; }"),
+            vec![
+                Instruction::Global("main".to_string()),
+                Instruction::Label("main".to_string()),
+                Instruction::Push(Reg::Rbp),
+                Instruction::MovQ(Reg::Rsp, Reg::Rbp),
+                Instruction::AllocateStack(0),
+                Instruction::Mov(Operand::Imm(5), eax()),
+                Instruction::MovQ(Reg::Rbp, Reg::Rsp),
+                Instruction::Pop(Reg::Rbp),
+                Instruction::Ret,
+                // The implicit 'return 0' for falling off the end
+                Instruction::Mov(Operand::Imm(0), eax()),
+                Instruction::MovQ(Reg::Rbp, Reg::Rsp),
+                Instruction::Pop(Reg::Rbp),
+                Instruction::Ret,
+            ]
+        );
+    }
+
+    #[test]
+    fn emitted_text_for_return_42() {
+        let asm = emit(&gen_source("int main(void) { return 42; }"));
+        assert!(asm.contains("\t.globl main\nmain:\n"));
+        assert!(asm.contains("\tmovl $42, %eax\n"));
+        assert!(asm.contains("\tret\n"));
+    }
+
+    #[test]
+    fn negation_and_complement() {
+        assert_eq!(gen_body("return -5;"), vec![Instruction::Mov(Operand::Imm(5), eax()), Instruction::Neg(eax())]);
+        assert_eq!(gen_body("return ~5;"), vec![Instruction::Mov(Operand::Imm(5), eax()), Instruction::Not(eax())]);
+        assert!(emit(&gen_source("int main(void) { return -5; }")).contains("\tmovl $5, %eax\n\tnegl %eax\n"));
+    }
+
+    #[test]
+    fn logical_not_compares_with_zero_and_sets_on_equal() {
+        assert_eq!(
+            gen_body("return !0;"),
+            vec![
+                Instruction::Mov(Operand::Imm(0), eax()),
+                Instruction::Cmp(Operand::Imm(0), eax()),
+                Instruction::Mov(Operand::Imm(0), eax()),
+                Instruction::SetCC(CondCode::E, Reg::Eax),
+            ]
+        );
+        assert!(emit(&gen_source("int main(void) { return !0; }")).contains("\tsete %al\n"));
+    }
+
+    // The instructions of a binary operation on two constants, up to the operator itself
+    fn binary_prefix(left: i32, right: i32) -> Vec<Instruction> {
+        vec![
+            Instruction::Mov(Operand::Imm(left), eax()),
+            Instruction::Push(Reg::Eax),
+            Instruction::Mov(Operand::Imm(right), eax()),
+            Instruction::Mov(eax(), ecx()),
+            Instruction::Pop(Reg::Eax),
+        ]
+    }
+
+    #[test]
+    fn division_sign_extends_and_leaves_the_quotient_in_eax() {
+        let mut expected = binary_prefix(6, 4);
+        expected.extend([Instruction::Cdq, Instruction::Idiv(ecx())]);
+        assert_eq!(gen_body("return 6 / 4;"), expected);
+    }
+
+    #[test]
+    fn remainder_is_read_from_edx() {
+        let mut expected = binary_prefix(7, 3);
+        expected.extend([Instruction::Cdq, Instruction::Idiv(ecx()), Instruction::Mov(Operand::Register(Reg::Edx), eax())]);
+        assert_eq!(gen_body("return 7 % 3;"), expected);
+    }
+
+    #[test]
+    fn arithmetic_operators() {
+        for (op, alu) in [("+", AluOp::Add), ("-", AluOp::Sub), ("*", AluOp::Imul)] {
+            let mut expected = binary_prefix(2, 3);
+            expected.push(Instruction::Binary(alu, ecx(), eax()));
+            assert_eq!(gen_body(&format!("return 2 {} 3;", op)), expected, "operator {}", op);
+        }
+    }
+
+    #[test]
+    fn comparison_operators_set_from_the_flags() {
+        for (op, cond) in [("<", CondCode::L), ("<=", CondCode::LE), (">", CondCode::G), (">=", CondCode::GE), ("==", CondCode::E), ("!=", CondCode::NE)] {
+            let mut expected = binary_prefix(2, 3);
+            expected.extend([Instruction::Cmp(ecx(), eax()), Instruction::Mov(Operand::Imm(0), eax()), Instruction::SetCC(cond, Reg::Eax)]);
+            assert_eq!(gen_body(&format!("return 2 {} 3;", op)), expected, "operator {}", op);
+        }
+    }
+
+    #[test]
+    fn bitwise_operators_and_shifts() {
+        for (op, alu) in [("&", AluOp::And), ("|", AluOp::Or), ("^", AluOp::Xor), ("<<", AluOp::Sal), (">>", AluOp::Sar)] {
+            let mut expected = binary_prefix(6, 1);
+            expected.push(Instruction::Binary(alu, ecx(), eax()));
+            assert_eq!(gen_body(&format!("return 6 {} 1;", op)), expected, "operator {}", op);
+        }
+        let asm = emit(&gen_source("int main(void) { return 6 << 1; }"));
+        assert!(asm.contains("\tsall %cl, %eax\n"));
+    }
+
+    #[test]
+    fn locals_get_stack_slots_in_an_aligned_frame() {
+        let instructions = gen_source("int main(void) { int x = 1; int y = 2; return x + y; }");
+        assert!(instructions.contains(&Instruction::AllocateStack(16)));
+        assert!(instructions.contains(&Instruction::Mov(eax(), Operand::Stack(-4))));
+        assert!(instructions.contains(&Instruction::Mov(eax(), Operand::Stack(-8))));
+        let asm = emit(&instructions);
+        assert!(asm.contains("\tpushq %rbp\n\tmovq %rsp, %rbp\n\tsubq $16, %rsp\n"));
+        assert!(asm.contains("\tmovl %eax, -4(%rbp)\n"));
+    }
+
+    #[test]
+    fn frame_is_rounded_up_to_sixteen_bytes() {
+        let instructions = gen_source("int main(void) { int a; int b; int c; int d; int e; return 0; }");
+        assert!(instructions.contains(&Instruction::AllocateStack(32)));
+    }
+
+    #[test]
+    fn fresh_labels_never_repeat() {
+        let mut labels = LabelGen { counter: 0 };
+        let first = labels.fresh("if_end");
+        let second = labels.fresh("if_end");
+        assert_ne!(first, second);
+        assert!(first.starts_with(".Lif_end_"));
+    }
+
+    #[test]
+    fn logical_and_jumps_out_on_the_first_zero_operand() {
+        let false_label = ".Land_false_0".to_string();
+        assert_eq!(
+            gen_body("return 0 && 1;"),
+            vec![
+                Instruction::Mov(Operand::Imm(0), eax()),
+                Instruction::Cmp(Operand::Imm(0), eax()),
+                Instruction::JmpCC(CondCode::E, false_label.clone()),
+                Instruction::Mov(Operand::Imm(1), eax()),
+                Instruction::Cmp(Operand::Imm(0), eax()),
+                Instruction::JmpCC(CondCode::E, false_label.clone()),
+                Instruction::Mov(Operand::Imm(1), eax()),
+                Instruction::Jmp(".Land_end_1".to_string()),
+                Instruction::Label(false_label),
+                Instruction::Mov(Operand::Imm(0), eax()),
+                Instruction::Label(".Land_end_1".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn if_else_compares_and_jumps_to_the_else_branch() {
+        let all = gen_source("int main(void) { int x = 0; if (x) return 1; else return 2; }");
+        let label_names: Vec<String> = all.iter().filter_map(|instruction| match instruction {
+            Instruction::Label(name) if name.starts_with(".L") => Some(name.clone()),
+            _ => None,
+        }).collect();
+        assert_eq!(label_names.len(), 2);
+        let jump = all.iter().position(|instruction| *instruction == Instruction::JmpCC(CondCode::E, label_names[0].clone())).unwrap();
+        assert_eq!(all[jump - 1], Instruction::Cmp(Operand::Imm(0), eax()));
+        assert!(label_names[0].starts_with(".Lif_else_") && label_names[1].starts_with(".Lif_end_"));
+    }
+
+    #[test]
+    fn break_jumps_to_the_end_of_its_while_loop() {
+        let instructions = gen_source("int main(void) { int x = 0; while (1) { x = x + 1; if (x > 3) break; } return x; }");
+        let end = instructions.iter().find_map(|instruction| match instruction {
+            Instruction::Label(name) if name.starts_with(".Lwhile_end_") => Some(name.clone()),
+            _ => None,
+        }).unwrap();
+        let start = instructions.iter().find_map(|instruction| match instruction {
+            Instruction::Label(name) if name.starts_with(".Lwhile_start_") => Some(name.clone()),
+            _ => None,
+        }).unwrap();
+        // One jump to the end from the condition test and one from the 'break'
+        assert!(instructions.contains(&Instruction::JmpCC(CondCode::E, end.clone())));
+        assert!(instructions.contains(&Instruction::Jmp(end)));
+        assert!(instructions.contains(&Instruction::Jmp(start)));
+    }
+
+    #[test]
+    fn do_while_runs_the_body_before_testing_the_condition() {
+        let instructions = gen_body("int x = 0; do x = x + 1; while (x < 3); return x;");
+        let start = instructions.iter().position(|instruction| matches!(instruction, Instruction::Label(name) if name.starts_with(".Ldo_start_"))).unwrap();
+        let body = instructions.iter().position(|instruction| *instruction == Instruction::Binary(AluOp::Add, ecx(), eax())).unwrap();
+        let test = instructions.iter().position(|instruction| matches!(instruction, Instruction::SetCC(CondCode::L, _))).unwrap();
+        let back = instructions.iter().position(|instruction| matches!(instruction, Instruction::JmpCC(CondCode::NE, _))).unwrap();
+        assert!(start < body && body < test && test < back);
+        assert!(!instructions[..start].iter().any(|instruction| matches!(instruction, Instruction::JmpCC(..) | Instruction::Jmp(_))));
+    }
+
+
+    #[test]
+    fn parameters_are_spilled_from_the_system_v_registers() {
+        let instructions = gen_source("int add(int a, int b, int c, int d, int e, int f) { return a; }");
+        let start = instructions.iter().position(|instruction| matches!(instruction, Instruction::AllocateStack(_))).unwrap() + 1;
+        let registers = [Reg::Edi, Reg::Esi, Reg::Edx, Reg::Ecx, Reg::R8d, Reg::R9d];
+        let expected: Vec<Instruction> =
+            registers.into_iter().zip(1..).map(|(reg, i)| Instruction::Mov(Operand::Register(reg), Operand::Stack(-4 * i))).collect();
+        assert_eq!(instructions[start..start + 6], expected);
+        let error = gen(&parse(tokenize("int f(int a, int b, int c, int d, int e, int f, int g) { return a; }").unwrap()).unwrap());
+        assert!(error.unwrap_err().contains("more than 6 parameters"));
+    }
+
+    #[test]
+    fn call_pops_its_arguments_into_registers() {
+        assert_eq!(
+            gen_body("return f(1, 2);")[..7],
+            [
+                Instruction::Mov(Operand::Imm(1), eax()),
+                Instruction::Push(Reg::Eax),
+                Instruction::Mov(Operand::Imm(2), eax()),
+                Instruction::Push(Reg::Eax),
+                Instruction::Pop(Reg::Esi),
+                Instruction::Pop(Reg::Edi),
+                Instruction::Call("f".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn call_under_a_pushed_temporary_pads_the_stack() {
+        let instructions = gen_body("return 1 + f();");
+        let call = instructions.iter().position(|instruction| *instruction == Instruction::Call("f".to_string())).unwrap();
+        assert_eq!(instructions[call - 1], Instruction::AllocateStack(8));
+        assert_eq!(instructions[call + 1], Instruction::DeallocateStack(8));
+        assert!(emit(&instructions).contains("\tsubq $8, %rsp\n\tcall f\n\taddq $8, %rsp\n"));
+    }
+}
@@ -0,0 +1,519 @@
+use std::fmt; // Import formatting traits for printing types
+
+/// Represents an entire program consisting of function definitions and file-scope variable declarations, each in source order.
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub functions: Vec<Function>,
+    pub variables: Vec<Declaration>, // Declarations outside any function, like 'static int x;'
+}
+
+/// Represents a function definition with a name, its parameters, and a body consisting of a block of statements.
+#[derive(Debug, Clone)]
+pub struct Function {
+    pub storage: Option<StorageClass>, // 'static' or 'extern' before the return type, if written
+    pub return_type: Type,
+    pub name: String,
+    pub params: Vec<(Type, String)>,
+    pub unspecified_params: bool, // True for '()', which says nothing about the parameters, unlike '(void)'
+    pub body: Vec<BlockItem>,
+}
+
+/// Represents the types a declaration can name.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Type {
+    Int,
+    Void,
+    Double,
+}
+
+/// Represents the storage-class specifiers a declaration can start with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StorageClass {
+    Static, // 'static'
+    Extern, // 'extern'
+}
+
+/// Represents one entry of a block: either a declaration or a statement.
+#[derive(Debug, Clone)]
+pub enum BlockItem {
+    Statement(Statement),
+    Declaration(Declaration),
+}
+
+/// Represents a variable declaration with an optional initializer.
+#[derive(Debug, Clone)]
+pub struct Declaration {
+    pub storage: Option<StorageClass>, // 'static' or 'extern' before the type, if written
+    pub name: String,
+    pub init: Option<Expr>,
+}
+
+/// Represents different kinds of statements.
+#[derive(Debug, Clone)]
+pub enum Statement {
+    Return(Option<Expr>), // 'None' for a bare 'return;'
+    Expression(Expr),
+    If { cond: Expr, then: Box<Statement>, else_branch: Option<Box<Statement>> },
+    While { cond: Expr, body: Box<Statement> },
+    DoWhile { body: Box<Statement>, cond: Expr },
+    For { init: Option<ForInit>, cond: Option<Expr>, post: Option<Expr>, body: Box<Statement> },
+    Break,
+    Continue,
+    Block(Vec<BlockItem>),
+    Goto(String),
+    Label(String, Box<Statement>),
+    Switch { value: Expr, body: Box<Statement> },
+    Case(Expr, Box<Statement>),
+    Default(Box<Statement>),
+}
+
+/// Represents the first clause of a for loop header.
+#[derive(Debug, Clone)]
+pub enum ForInit {
+    Declaration(Declaration),
+    Expression(Expr),
+}
+
+/// Represents different kinds of expressions.
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Constant(i32),
+    FloatConstant(f64), // '1.5', which has type 'double'
+    Var(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Assign(Box<Expr>, Box<Expr>),
+    Call { name: String, args: Vec<Expr> },
+    Conditional { cond: Box<Expr>, then: Box<Expr>, else_: Box<Expr> },
+    Subscript { base: Box<Expr>, index: Box<Expr> }, // 'base[index]'
+    Deref(Box<Expr>),  // '*p'
+    AddrOf(Box<Expr>), // '&x'
+    Member { base: Box<Expr>, field: String },    // 's.field'
+    PtrMember { base: Box<Expr>, field: String }, // 'p->field'
+    PrefixInc(Box<Expr>),  // '++x', whose value is the updated operand
+    PrefixDec(Box<Expr>),  // '--x'
+    PostfixInc(Box<Expr>), // 'x++', whose value is the operand before the update
+    PostfixDec(Box<Expr>), // 'x--'
+    SizeOf(Box<Expr>), // 'sizeof x', which never evaluates its operand
+    SizeOfType(Type),      // 'sizeof(int)'
+    Cast(Type, Box<Expr>), // '(int)x', which converts the operand's value to the type
+    Comma(Box<Expr>, Box<Expr>), // 'a, b', which evaluates 'a', discards it, and yields 'b'
+}
+
+/// Represents the prefix operators that take a single operand.
+#[derive(Debug, Clone)]
+pub enum UnaryOp {
+    Negate,     // '-'
+    Complement, // '~'
+    Not,        // '!'
+}
+
+/// Represents the infix operators that take two operands.
+#[derive(Debug, Clone)]
+pub enum BinaryOp {
+    Add,          // '+'
+    Subtract,     // '-'
+    Multiply,     // '*'
+    Divide,       // '/'
+    Remainder,    // '%'
+    ShiftLeft,    // '<<'
+    ShiftRight,   // '>>'
+    Less,         // '<'
+    LessEqual,    // '<='
+    Greater,      // '>'
+    GreaterEqual, // '>='
+    Equal,        // '=='
+    NotEqual,     // '!='
+    BitAnd,       // '&'
+    BitXor,       // '^'
+    BitOr,        // '|'
+    And,          // '&&'
+    Or,           // '||'
+}
+
+impl Type {
+    /// Returns the size in bytes of a value of this type, or `None` for `void`, which has no values.
+    pub fn size(&self) -> Option<i32> {
+        match self {
+            Type::Int => Some(4),
+            Type::Void => None,
+            Type::Double => Some(8),
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Void => write!(f, "void"),
+            Type::Double => write!(f, "double"),
+        }
+    }
+}
+
+impl fmt::Display for StorageClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageClass::Static => write!(f, "static"),
+            StorageClass::Extern => write!(f, "extern"),
+        }
+    }
+}
+
+impl UnaryOp {
+    /// Returns the source spelling of the operator.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOp::Negate => "-",
+            UnaryOp::Complement => "~",
+            UnaryOp::Not => "!",
+        }
+    }
+}
+
+impl BinaryOp {
+    /// Returns the source spelling of the operator.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Subtract => "-",
+            BinaryOp::Multiply => "*",
+            BinaryOp::Divide => "/",
+            BinaryOp::Remainder => "%",
+            BinaryOp::ShiftLeft => "<<",
+            BinaryOp::ShiftRight => ">>",
+            BinaryOp::Less => "<",
+            BinaryOp::LessEqual => "<=",
+            BinaryOp::Greater => ">",
+            BinaryOp::GreaterEqual => ">=",
+            BinaryOp::Equal => "==",
+            BinaryOp::NotEqual => "!=",
+            BinaryOp::BitAnd => "&",
+            BinaryOp::BitXor => "^",
+            BinaryOp::BitOr => "|",
+            BinaryOp::And => "&&",
+            BinaryOp::Or => "||",
+        }
+    }
+
+    /// Returns how tightly the operator binds; a higher number binds more tightly.
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Multiply | BinaryOp::Divide | BinaryOp::Remainder => 50,
+            BinaryOp::Add | BinaryOp::Subtract => 45,
+            BinaryOp::ShiftLeft | BinaryOp::ShiftRight => 40,
+            BinaryOp::Less | BinaryOp::LessEqual | BinaryOp::Greater | BinaryOp::GreaterEqual => 35,
+            BinaryOp::Equal | BinaryOp::NotEqual => 30,
+            BinaryOp::BitAnd => 25,
+            BinaryOp::BitXor => 20,
+            BinaryOp::BitOr => 15,
+            BinaryOp::And => 10,
+            BinaryOp::Or => 5,
+        }
+    }
+}
+
+/// Renders the program back to C source, one statement per line with two-space indentation.
+pub fn unparse(program: &Program) -> String {
+    // The file-scope variables come first, so every function can see them
+    let variables: String = program.variables.iter().map(|declaration| format!("{};\n", unparse_declaration(declaration))).collect();
+    let functions: Vec<String> = program.functions.iter().map(unparse_function).collect();
+    let separator = if variables.is_empty() || functions.is_empty() { "" } else { "\n" };
+    format!("{}{}{}", variables, separator, functions.join("\n")) // A blank line between functions
+}
+
+// Render one function definition
+fn unparse_function(function: &Function) -> String {
+    let params = if function.params.is_empty() {
+        if function.unspecified_params { String::new() } else { "void".to_string() }
+    } else {
+        let params: Vec<String> = function.params.iter().map(|(ty, name)| format!("{} {}", ty, name)).collect();
+        params.join(", ")
+    };
+    let mut out = format!("{}{} {}({}) {{\n", storage_prefix(&function.storage), function.return_type, function.name, params);
+    for item in &function.body {
+        unparse_block_item(item, 1, &mut out);
+    }
+    out.push_str("}\n");
+    out
+}
+
+// A storage class followed by the space that separates it from the type, or nothing
+fn storage_prefix(storage: &Option<StorageClass>) -> String {
+    storage.as_ref().map(|storage| format!("{} ", storage)).unwrap_or_default()
+}
+
+// Indentation for the given nesting depth
+fn indentation(depth: usize) -> String {
+    "  ".repeat(depth)
+}
+
+// Render a declaration or statement on its own line(s)
+fn unparse_block_item(item: &BlockItem, depth: usize, out: &mut String) {
+    match item {
+        BlockItem::Declaration(declaration) => {
+            out.push_str(&indentation(depth));
+            out.push_str(&unparse_declaration(declaration));
+            out.push_str(";\n");
+        }
+        BlockItem::Statement(statement) => {
+            out.push_str(&indentation(depth));
+            unparse_statement(statement, depth, out);
+        }
+    }
+}
+
+// Render a declaration without its trailing ';'
+fn unparse_declaration(declaration: &Declaration) -> String {
+    let storage = storage_prefix(&declaration.storage);
+    match &declaration.init {
+        Some(init) => format!("{}int {} = {}", storage, declaration.name, unparse_operand(init, 1)),
+        None => format!("{}int {}", storage, declaration.name),
+    }
+}
+
+// Render a statement whose indentation has already been written, ending with a newline
+fn unparse_statement(statement: &Statement, depth: usize, out: &mut String) {
+    match statement {
+        Statement::Return(Some(expr)) => out.push_str(&format!("return {};\n", unparse_expr(expr))),
+        Statement::Return(None) => out.push_str("return;\n"),
+        Statement::Expression(expr) => out.push_str(&format!("{};\n", unparse_expr(expr))),
+        Statement::If { cond, then, else_branch } => {
+            out.push_str(&format!("if ({})", unparse_expr(cond)));
+            unparse_body(then, depth, out);
+            if let Some(else_branch) = else_branch {
+                if matches!(**then, Statement::Block(_)) {
+                    out.pop(); // Keep 'else' on the line of the closing brace
+                    out.push_str(" else");
+                } else {
+                    out.push_str(&indentation(depth));
+                    out.push_str("else");
+                }
+                if matches!(**else_branch, Statement::If { .. }) {
+                    out.push(' '); // Chain 'else if' on one line
+                    unparse_statement(else_branch, depth, out);
+                } else {
+                    unparse_body(else_branch, depth, out);
+                }
+            }
+        }
+        Statement::While { cond, body } => {
+            out.push_str(&format!("while ({})", unparse_expr(cond)));
+            unparse_body(body, depth, out);
+        }
+        Statement::DoWhile { body, cond } => {
+            out.push_str("do");
+            unparse_body(body, depth, out);
+            if matches!(**body, Statement::Block(_)) {
+                out.pop(); // Keep 'while' on the line of the closing brace
+                out.push(' ');
+            } else {
+                out.push_str(&indentation(depth));
+            }
+            out.push_str(&format!("while ({});\n", unparse_expr(cond)));
+        }
+        Statement::For { init, cond, post, body } => {
+            let init = match init {
+                Some(ForInit::Declaration(declaration)) => unparse_declaration(declaration),
+                Some(ForInit::Expression(expr)) => unparse_expr(expr),
+                None => String::new(),
+            };
+            let clause = |expr: &Option<Expr>| expr.as_ref().map(|e| format!(" {}", unparse_expr(e))).unwrap_or_default();
+            out.push_str(&format!("for ({};{};{})", init, clause(cond), clause(post)));
+            unparse_body(body, depth, out);
+        }
+        Statement::Break => out.push_str("break;\n"),
+        Statement::Goto(label) => out.push_str(&format!("goto {};\n", label)),
+        Statement::Label(label, statement) => {
+            // The labeled statement follows on its own line at the same depth
+            out.push_str(&format!("{}:\n", label));
+            out.push_str(&indentation(depth));
+            unparse_statement(statement, depth, out);
+        }
+        Statement::Switch { value, body } => {
+            out.push_str(&format!("switch ({})", unparse_expr(value)));
+            unparse_body(body, depth, out);
+        }
+        Statement::Case(value, statement) => {
+            out.push_str(&format!("case {}:\n", unparse_expr(value)));
+            out.push_str(&indentation(depth));
+            unparse_statement(statement, depth, out);
+        }
+        Statement::Default(statement) => {
+            out.push_str("default:\n");
+            out.push_str(&indentation(depth));
+            unparse_statement(statement, depth, out);
+        }
+        Statement::Continue => out.push_str("continue;\n"),
+        Statement::Block(items) => {
+            out.push_str("{\n");
+            for item in items {
+                unparse_block_item(item, depth + 1, out);
+            }
+            out.push_str(&indentation(depth));
+            out.push_str("}\n");
+        }
+    }
+}
+
+// Render the body of an if, else, or loop: a block stays on the header's line, while any
+// other statement goes on the next line, indented one level deeper
+fn unparse_body(body: &Statement, depth: usize, out: &mut String) {
+    if matches!(body, Statement::Block(_)) {
+        out.push(' ');
+        unparse_statement(body, depth, out);
+    } else {
+        out.push('\n');
+        out.push_str(&indentation(depth + 1));
+        unparse_statement(body, depth + 1, out);
+    }
+}
+
+// Precedence of a prefix operator, just below the postfix operators like '[]'
+const UNARY_PREC: u8 = u8::MAX - 1;
+
+// Precedence of an expression as an operand; atoms and postfix expressions bind tightest
+fn expr_precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::Comma(..) => 0,
+        Expr::Assign(..) => 1,
+        Expr::Conditional { .. } => 3,
+        Expr::Binary(op, ..) => op.precedence(),
+        Expr::Unary(..)
+        | Expr::Deref(_)
+        | Expr::AddrOf(_)
+        | Expr::PrefixInc(_)
+        | Expr::PrefixDec(_)
+        | Expr::SizeOf(_)
+        | Expr::SizeOfType(_)
+        | Expr::Cast(..) => UNARY_PREC,
+        Expr::Constant(_)
+        | Expr::FloatConstant(_)
+        | Expr::Var(_)
+        | Expr::Call { .. }
+        | Expr::Subscript { .. }
+        | Expr::Member { .. }
+        | Expr::PtrMember { .. }
+        | Expr::PostfixInc(_)
+        | Expr::PostfixDec(_) => u8::MAX,
+    }
+}
+
+// Render an operand, adding parentheses when it binds more loosely than `min_prec`
+fn unparse_operand(expr: &Expr, min_prec: u8) -> String {
+    if expr_precedence(expr) < min_prec {
+        format!("({})", unparse_expr(expr))
+    } else {
+        unparse_expr(expr)
+    }
+}
+
+// Render an expression with only the parentheses its structure needs
+fn unparse_expr(expr: &Expr) -> String {
+    match expr {
+        Expr::Constant(value) => value.to_string(),
+        Expr::FloatConstant(value) => format!("{:?}", value), // Always has a '.' or an exponent, so it reads back as a 'double'
+        Expr::Var(name) => name.clone(),
+        Expr::Unary(op, operand) => {
+            let operand = unparse_operand(operand, UNARY_PREC);
+            // Keep '- -x' from running together into '--x'
+            if matches!(op, UnaryOp::Negate) && operand.starts_with('-') {
+                format!("- {}", operand)
+            } else {
+                format!("{}{}", op.symbol(), operand)
+            }
+        }
+        // Operators associate to the left, so a right operand at the same level needs parentheses
+        Expr::Binary(op, left, right) => format!(
+            "{} {} {}",
+            unparse_operand(left, op.precedence()),
+            op.symbol(),
+            unparse_operand(right, op.precedence() + 1)
+        ),
+        Expr::Assign(target, value) => format!("{} = {}", unparse_expr(target), unparse_operand(value, 1)),
+        // The condition can't itself be an unparenthesized conditional, but the else branch can
+        Expr::Conditional { cond, then, else_ } => {
+            format!("{} ? {} : {}", unparse_operand(cond, 4), unparse_expr(then), unparse_operand(else_, 3))
+        }
+        Expr::Call { name, args } => {
+            // A comma expression as an argument needs parentheses to stay one argument
+            let args: Vec<String> = args.iter().map(|arg| unparse_operand(arg, 1)).collect();
+            format!("{}({})", name, args.join(", "))
+        }
+        Expr::Subscript { base, index } => format!("{}[{}]", unparse_operand(base, u8::MAX), unparse_expr(index)),
+        Expr::Member { base, field } => format!("{}.{}", unparse_operand(base, u8::MAX), field),
+        Expr::PtrMember { base, field } => format!("{}->{}", unparse_operand(base, u8::MAX), field),
+        Expr::PostfixInc(operand) => format!("{}++", unparse_operand(operand, u8::MAX)),
+        Expr::PostfixDec(operand) => format!("{}--", unparse_operand(operand, u8::MAX)),
+        Expr::PrefixInc(operand) => format!("++{}", unparse_operand(operand, UNARY_PREC)),
+        Expr::PrefixDec(operand) => format!("--{}", unparse_operand(operand, UNARY_PREC)),
+        Expr::Deref(operand) => format!("*{}", unparse_operand(operand, UNARY_PREC)),
+        Expr::SizeOf(operand) => format!("sizeof {}", unparse_operand(operand, UNARY_PREC)),
+        Expr::SizeOfType(ty) => format!("sizeof({})", ty),
+        Expr::Cast(ty, operand) => format!("({}){}", ty, unparse_operand(operand, UNARY_PREC)),
+        Expr::Comma(left, right) => format!("{}, {}", unparse_expr(left), unparse_operand(right, 1)),
+        Expr::AddrOf(operand) => format!("&{}", unparse_operand(operand, UNARY_PREC)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::{tokenize, Token};
+    use crate::parser::parse;
+
+    #[test]
+    fn program_built_by_hand_debug_formats() {
+        let program = Program {
+            functions: vec![Function {
+                storage: None,
+                return_type: Type::Int,
+                name: "main".to_string(),
+                params: vec![],
+                unspecified_params: false,
+                body: vec![BlockItem::Statement(Statement::Return(Some(Expr::Constant(7))))],
+            }],
+            variables: vec![],
+        };
+        assert_eq!(
+            format!("{:?}", program),
+            "Program { functions: [Function { storage: None, return_type: Int, name: \"main\", params: [], unspecified_params: false, body: [Statement(Return(Some(Constant(7))))] }], variables: [] }"
+        );
+    }
+
+    // Every statement and expression kind, written the way the unparser renders it
+    const EVERY_CONSTRUCT: &str = "static int g;
+    extern int h = 2;
+    int main(void) {
+        int x = 1;
+        int y;
+        y = x = -~!x;
+        y = - -y;
+        if (x < y) y = 2; else if (x) { y = 3; } else y = 4;
+        while (x != 0) {
+            x = x - (y - 1);
+            if (x == 5) break; else continue;
+        }
+        for (int i = 0; i <= 10; i = i + 1) y = y * (i + 2) / 3 % 4;
+        for (x = 0; x > 1 >= 2;) {}
+        for (;;) break;
+        {
+            int z = x << 1 >> 2 & 3 ^ 4 | 5 && x || !y;
+            static int w;
+        }
+        return y;
+    }";
+
+    // The token values of a source, without their positions
+    fn token_values(source: &str) -> Vec<Token> {
+        tokenize(source).unwrap().into_iter().map(|token| token.value).collect()
+    }
+
+    #[test]
+    fn unparsed_source_lexes_to_the_original_tokens() {
+        let program = parse(tokenize(EVERY_CONSTRUCT).unwrap()).unwrap();
+        let source = unparse(&program);
+        assert_eq!(token_values(&source), token_values(EVERY_CONSTRUCT), "{}", source);
+    }
+}
@@ -0,0 +1,320 @@
+// Graphviz rendering of the AST, for viewing the tree with 'dot -Tpng'
+use crate::ast::{BlockItem, Declaration, Expr, ForInit, Function, Program, Statement}; // Import the AST node types
+
+// Render the program as a DOT digraph with one node per function, statement, and expression.
+// Edges run from parent to child, labeled with the child's role when it has one.
+pub fn to_dot(program: &Program) -> String {
+    let mut graph = DotGraph { out: String::from("digraph ast {\n  node [shape=box];\n"), next_id: 0 };
+    let root = graph.node("Program");
+    for declaration in &program.variables {
+        let child = graph.declaration(declaration);
+        graph.edge(root, child, None);
+    }
+    for function in &program.functions {
+        let child = graph.function(function);
+        graph.edge(root, child, None);
+    }
+    graph.out.push_str("}\n");
+    graph.out
+}
+
+// The DOT text built so far
+struct DotGraph {
+    out: String,
+    next_id: usize, // Id of the next node, so every node gets a unique one
+}
+
+impl DotGraph {
+    // Add a node with the given label and return its id
+    fn node(&mut self, label: &str) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        // Escape the label so quotes and backslashes in it can't end the string early
+        let label = label.replace('\\', "\\\\").replace('"', "\\\"");
+        self.out.push_str(&format!("  n{} [label=\"{}\"];\n", id, label));
+        id
+    }
+
+    // Add an edge from a parent to a child node
+    fn edge(&mut self, parent: usize, child: usize, label: Option<&str>) {
+        match label {
+            Some(label) => self.out.push_str(&format!("  n{} -> n{} [label=\"{}\"];\n", parent, child, label)),
+            None => self.out.push_str(&format!("  n{} -> n{};\n", parent, child)),
+        }
+    }
+
+    // Add a child statement under a parent, with the child's role as the edge label
+    fn statement_edge(&mut self, parent: usize, statement: &Statement, label: &str) {
+        let child = self.statement(statement);
+        self.edge(parent, child, Some(label));
+    }
+
+    // Add a child expression under a parent, with the child's role as the edge label
+    fn expr_edge(&mut self, parent: usize, expr: &Expr, label: Option<&str>) {
+        let child = self.expr(expr);
+        self.edge(parent, child, label);
+    }
+
+    // Add a function with its parameters in the label and its body items as children
+    fn function(&mut self, function: &Function) -> usize {
+        let mut params: Vec<String> = function.params.iter().map(|(ty, name)| format!("{} {}", ty, name)).collect();
+        if params.is_empty() && !function.unspecified_params {
+            params.push("void".to_string());
+        }
+        let storage = function.storage.as_ref().map(|storage| format!("{} ", storage)).unwrap_or_default();
+        let id = self.node(&format!("Function {}{} {}({})", storage, function.return_type, function.name, params.join(", ")));
+        self.block_items(id, &function.body);
+        id
+    }
+
+    // Add each item of a block as a child of the given node
+    fn block_items(&mut self, parent: usize, items: &[BlockItem]) {
+        for item in items {
+            let child = match item {
+                BlockItem::Declaration(declaration) => self.declaration(declaration),
+                BlockItem::Statement(statement) => self.statement(statement),
+            };
+            self.edge(parent, child, None);
+        }
+    }
+
+    // Add a declaration, with its initializer as a child
+    fn declaration(&mut self, declaration: &Declaration) -> usize {
+        let storage = declaration.storage.as_ref().map(|storage| format!("{} ", storage)).unwrap_or_default();
+        let id = self.node(&format!("Declaration {}int {}", storage, declaration.name));
+        if let Some(init) = &declaration.init {
+            self.expr_edge(id, init, Some("init"));
+        }
+        id
+    }
+
+    // Add a statement and everything nested in it
+    fn statement(&mut self, statement: &Statement) -> usize {
+        match statement {
+            Statement::Return(expr) => {
+                let id = self.node("Return");
+                if let Some(expr) = expr {
+                    self.expr_edge(id, expr, None);
+                }
+                id
+            }
+            Statement::Expression(expr) => {
+                let id = self.node("Expression");
+                self.expr_edge(id, expr, None);
+                id
+            }
+            Statement::If { cond, then, else_branch } => {
+                let id = self.node("If");
+                self.expr_edge(id, cond, Some("cond"));
+                self.statement_edge(id, then, "then");
+                if let Some(else_branch) = else_branch {
+                    self.statement_edge(id, else_branch, "else");
+                }
+                id
+            }
+            Statement::While { cond, body } => {
+                let id = self.node("While");
+                self.expr_edge(id, cond, Some("cond"));
+                self.statement_edge(id, body, "body");
+                id
+            }
+            Statement::DoWhile { body, cond } => {
+                let id = self.node("DoWhile");
+                self.statement_edge(id, body, "body");
+                self.expr_edge(id, cond, Some("cond"));
+                id
+            }
+            Statement::For { init, cond, post, body } => {
+                let id = self.node("For");
+                match init {
+                    Some(ForInit::Declaration(declaration)) => {
+                        let child = self.declaration(declaration);
+                        self.edge(id, child, Some("init"));
+                    }
+                    Some(ForInit::Expression(expr)) => self.expr_edge(id, expr, Some("init")),
+                    None => {}
+                }
+                if let Some(cond) = cond {
+                    self.expr_edge(id, cond, Some("cond"));
+                }
+                if let Some(post) = post {
+                    self.expr_edge(id, post, Some("post"));
+                }
+                self.statement_edge(id, body, "body");
+                id
+            }
+            Statement::Switch { value, body } => {
+                let id = self.node("Switch");
+                self.expr_edge(id, value, Some("value"));
+                self.statement_edge(id, body, "body");
+                id
+            }
+            Statement::Case(value, statement) => {
+                let id = self.node("Case");
+                self.expr_edge(id, value, Some("value"));
+                self.statement_edge(id, statement, "body");
+                id
+            }
+            Statement::Default(statement) => {
+                let id = self.node("Default");
+                self.statement_edge(id, statement, "body");
+                id
+            }
+            Statement::Label(label, statement) => {
+                let id = self.node(&format!("Label {}", label));
+                self.statement_edge(id, statement, "body");
+                id
+            }
+            Statement::Goto(label) => self.node(&format!("Goto {}", label)),
+            Statement::Break => self.node("Break"),
+            Statement::Continue => self.node("Continue"),
+            Statement::Block(items) => {
+                let id = self.node("Block");
+                self.block_items(id, items);
+                id
+            }
+        }
+    }
+
+    // Add an expression and its operands
+    fn expr(&mut self, expr: &Expr) -> usize {
+        match expr {
+            Expr::Constant(value) => self.node(&format!("Constant {}", value)),
+            Expr::FloatConstant(value) => self.node(&format!("FloatConstant {:?}", value)),
+            Expr::Var(name) => self.node(&format!("Var {}", name)),
+            Expr::Unary(op, operand) => {
+                let id = self.node(&format!("Unary {}", op.symbol()));
+                self.expr_edge(id, operand, None);
+                id
+            }
+            Expr::Binary(op, left, right) => {
+                let id = self.node(&format!("Binary {}", op.symbol()));
+                self.expr_edge(id, left, Some("left"));
+                self.expr_edge(id, right, Some("right"));
+                id
+            }
+            Expr::Assign(target, value) => {
+                let id = self.node("Assign");
+                self.expr_edge(id, target, Some("target"));
+                self.expr_edge(id, value, Some("value"));
+                id
+            }
+            Expr::Call { name, args } => {
+                let id = self.node(&format!("Call {}", name));
+                for (i, arg) in args.iter().enumerate() {
+                    self.expr_edge(id, arg, Some(&format!("arg {}", i + 1)));
+                }
+                id
+            }
+            Expr::Subscript { base, index } => {
+                let id = self.node("Subscript");
+                self.expr_edge(id, base, Some("base"));
+                self.expr_edge(id, index, Some("index"));
+                id
+            }
+            Expr::Member { base, field } => {
+                let id = self.node(&format!("Member .{}", field));
+                self.expr_edge(id, base, None);
+                id
+            }
+            Expr::PtrMember { base, field } => {
+                let id = self.node(&format!("PtrMember ->{}", field));
+                self.expr_edge(id, base, None);
+                id
+            }
+            Expr::PrefixInc(operand) | Expr::PrefixDec(operand) | Expr::PostfixInc(operand) | Expr::PostfixDec(operand) => {
+                let label = match expr {
+                    Expr::PrefixInc(_) => "PrefixInc",
+                    Expr::PrefixDec(_) => "PrefixDec",
+                    Expr::PostfixInc(_) => "PostfixInc",
+                    _ => "PostfixDec",
+                };
+                let id = self.node(label);
+                self.expr_edge(id, operand, None);
+                id
+            }
+            Expr::SizeOf(operand) => {
+                let id = self.node("SizeOf");
+                self.expr_edge(id, operand, None);
+                id
+            }
+            Expr::Comma(left, right) => {
+                let id = self.node("Comma");
+                self.expr_edge(id, left, Some("left"));
+                self.expr_edge(id, right, Some("right"));
+                id
+            }
+            Expr::SizeOfType(ty) => self.node(&format!("SizeOfType {}", ty)),
+            Expr::Cast(ty, operand) => {
+                let id = self.node(&format!("Cast {}", ty));
+                self.expr_edge(id, operand, None);
+                id
+            }
+            Expr::Deref(operand) => {
+                let id = self.node("Deref");
+                self.expr_edge(id, operand, None);
+                id
+            }
+            Expr::AddrOf(operand) => {
+                let id = self.node("AddrOf");
+                self.expr_edge(id, operand, None);
+                id
+            }
+            Expr::Conditional { cond, then, else_ } => {
+                let id = self.node("Conditional");
+                self.expr_edge(id, cond, Some("cond"));
+                self.expr_edge(id, then, Some("then"));
+                self.expr_edge(id, else_, Some("else"));
+                id
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    // The nodes of a DOT graph as (id, label) pairs, in the order they were written
+    fn nodes(dot: &str) -> Vec<(String, String)> {
+        dot.lines()
+            .filter_map(|line| {
+                let (id, rest) = line.trim().split_once(" [label=\"")?;
+                Some((id.to_string(), rest.strip_suffix("\"];")?.to_string()))
+            })
+            .filter(|(id, _)| !id.contains("->"))
+            .collect()
+    }
+
+    // The children of a node as (child id, edge label) pairs
+    fn children(dot: &str, parent: &str) -> Vec<(String, String)> {
+        let prefix = format!("{} -> ", parent);
+        dot.lines()
+            .filter_map(|line| {
+                let (child, rest) = line.trim().strip_prefix(&prefix)?.split_once(" [label=\"")?;
+                Some((child.to_string(), rest.strip_suffix("\"];")?.to_string()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn binary_node_points_at_two_distinct_constants() {
+        let dot = to_dot(&parse(tokenize("int main(void) { return 1+2; }").unwrap()).unwrap());
+        let nodes = nodes(&dot);
+        let label_of = |id: &str| nodes.iter().find(|(node, _)| node == id).unwrap().1.clone();
+        let mut ids: Vec<&String> = nodes.iter().map(|(id, _)| id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), nodes.len(), "{}", dot);
+
+        let (binary, _) = nodes.iter().find(|(_, label)| label == "Binary +").unwrap();
+        let operands = children(&dot, binary);
+        assert_eq!(operands.len(), 2, "{}", dot);
+        let (left, right) = (&operands[0], &operands[1]);
+        assert_ne!(left.0, right.0);
+        assert_eq!((label_of(&left.0), left.1.as_str()), ("Constant 1".to_string(), "left"));
+        assert_eq!((label_of(&right.0), right.1.as_str()), ("Constant 2".to_string(), "right"));
+    }
+}
@@ -0,0 +1,377 @@
+// Tree-walking interpreter: runs a checked program straight from its AST, so results can be
+// checked without an assembler or linker. Runs after name resolution, so every variable in a
+// function has a unique name and one map holds all of a call's locals.
+use crate::ast::{BinaryOp, BlockItem, Declaration, Expr, ForInit, Function, Program, Statement, Type, UnaryOp}; // Import the AST node types
+use crate::typecheck::value_type; // Import the type of a checked expression
+use std::collections::HashMap; // Import map types for functions and variables
+
+// How deeply calls may nest before the run is stopped, well before the interpreter's own
+// stack would overflow
+const MAX_CALL_DEPTH: usize = 1000;
+
+// Run the program's 'main' and return the value it returns
+pub fn run(program: &Program) -> Result<i32, String> {
+    let functions = program.functions.iter().map(|function| (function.name.as_str(), function)).collect();
+    let interpreter = Interpreter { functions };
+    // 'main' is called like any other function, so falling off its end returns 0
+    interpreter.call("main", Vec::new(), 0)
+}
+
+// How control leaves a statement
+enum Flow {
+    Normal,                // On to the next statement
+    Break,                 // Out of the innermost loop
+    Continue,              // To the next iteration of the innermost loop
+    Return(Option<Value>), // Out of the function, with its value unless it is 'void'
+}
+
+// The value of an expression; variables, parameters, and results are all 'int' so far, so a
+// 'double' only lives until it is converted or thrown away
+#[derive(Clone, Copy)]
+enum Value {
+    Int(i32),
+    Double(f64),
+}
+
+impl Value {
+    // Convert to an 'int' the way C does, dropping any fraction
+    fn to_int(self) -> Result<i32, String> {
+        match self {
+            Value::Int(value) => Ok(value),
+            // Rust's 'as' would saturate, but C leaves a value that doesn't fit undefined
+            Value::Double(value) if value.trunc() >= i32::MIN as f64 && value.trunc() <= i32::MAX as f64 => Ok(value as i32),
+            Value::Double(value) => Err(format!("Runtime Error: {:?} is out of range for 'int'", value)),
+        }
+    }
+
+    // Convert to a 'double', which holds every 'int' exactly
+    fn to_double(self) -> f64 {
+        match self {
+            Value::Int(value) => value as f64,
+            Value::Double(value) => value,
+        }
+    }
+
+    // Whether the value counts as true in a condition, which is whether it isn't zero
+    fn is_true(self) -> bool {
+        match self {
+            Value::Int(value) => value != 0,
+            Value::Double(value) => value != 0.0,
+        }
+    }
+
+    // Convert to a scalar type; the value of a cast to 'void' is never used
+    fn convert(self, ty: &Type) -> Result<Value, String> {
+        match ty {
+            Type::Int | Type::Void => self.to_int().map(Value::Int),
+            Type::Double => Ok(Value::Double(self.to_double())),
+        }
+    }
+}
+
+// The functions of the program being run
+struct Interpreter<'a> {
+    functions: HashMap<&'a str, &'a Function>, // Every function in the program, by name
+}
+
+// The variables of one call, by unique name; a declared variable without a value yet is absent
+type Locals = HashMap<String, i32>;
+
+impl Interpreter<'_> {
+    // Call a function with the values of its arguments
+    fn call(&self, name: &str, args: Vec<i32>, depth: usize) -> Result<i32, String> {
+        let Some(function) = self.functions.get(name) else {
+            return Err(format!("Runtime Error: call to '{}', which is not defined in this program", name));
+        };
+        if depth >= MAX_CALL_DEPTH {
+            return Err(format!("Runtime Error: calls nested more than {} deep", MAX_CALL_DEPTH));
+        }
+        let mut locals: Locals = function.params.iter().map(|(_, param)| param.clone()).zip(args).collect();
+        for item in &function.body {
+            match self.block_item(item, &mut locals, depth)? {
+                // Every function returns an 'int' so far; a 'void' function's result is never used
+                Flow::Return(value) => return value.map_or(Ok(0), Value::to_int),
+                // Nothing rejects these outside a loop before the program runs
+                Flow::Break => return Err(format!("Runtime Error: 'break' outside of a loop in function '{}'", name)),
+                Flow::Continue => return Err(format!("Runtime Error: 'continue' outside of a loop in function '{}'", name)),
+                Flow::Normal => {}
+            }
+        }
+        // Falling off the end returns 0, as 'main' is required to do
+        Ok(0)
+    }
+
+    // Run a declaration or statement
+    fn block_item(&self, item: &BlockItem, locals: &mut Locals, depth: usize) -> Result<Flow, String> {
+        match item {
+            BlockItem::Declaration(declaration) => {
+                self.declaration(declaration, locals, depth)?;
+                Ok(Flow::Normal)
+            }
+            BlockItem::Statement(statement) => self.statement(statement, locals, depth),
+        }
+    }
+
+    // Run a declaration; without an initializer the variable has no value yet
+    fn declaration(&self, declaration: &Declaration, locals: &mut Locals, depth: usize) -> Result<(), String> {
+        match &declaration.init {
+            Some(init) => {
+                let value = self.expr(init, locals, depth)?.to_int()?;
+                locals.insert(declaration.name.clone(), value);
+            }
+            // Entering the declaration again, as in a loop, forgets the last iteration's value
+            None => {
+                locals.remove(&declaration.name);
+            }
+        }
+        Ok(())
+    }
+
+    // Run a statement and report how control leaves it
+    fn statement(&self, statement: &Statement, locals: &mut Locals, depth: usize) -> Result<Flow, String> {
+        match statement {
+            Statement::Return(expr) => {
+                let value = match expr {
+                    Some(expr) => Some(self.expr(expr, locals, depth)?),
+                    None => None,
+                };
+                Ok(Flow::Return(value))
+            }
+            Statement::Expression(expr) => {
+                self.expr(expr, locals, depth)?;
+                Ok(Flow::Normal)
+            }
+            Statement::If { cond, then, else_branch } => {
+                if self.expr(cond, locals, depth)?.is_true() {
+                    self.statement(then, locals, depth)
+                } else if let Some(else_branch) = else_branch {
+                    self.statement(else_branch, locals, depth)
+                } else {
+                    Ok(Flow::Normal)
+                }
+            }
+            Statement::While { cond, body } => {
+                while self.expr(cond, locals, depth)?.is_true() {
+                    match self.statement(body, locals, depth)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            Statement::DoWhile { body, cond } => {
+                loop {
+                    match self.statement(body, locals, depth)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                    if !self.expr(cond, locals, depth)?.is_true() {
+                        break;
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            Statement::For { init, cond, post, body } => {
+                match init {
+                    Some(ForInit::Declaration(declaration)) => self.declaration(declaration, locals, depth)?,
+                    Some(ForInit::Expression(expr)) => {
+                        self.expr(expr, locals, depth)?;
+                    }
+                    None => {}
+                }
+                loop {
+                    // A missing condition is always true
+                    if let Some(cond) = cond {
+                        if !self.expr(cond, locals, depth)?.is_true() {
+                            break;
+                        }
+                    }
+                    match self.statement(body, locals, depth)? {
+                        Flow::Break => break,
+                        Flow::Return(value) => return Ok(Flow::Return(value)),
+                        Flow::Normal | Flow::Continue => {}
+                    }
+                    if let Some(post) = post {
+                        self.expr(post, locals, depth)?;
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            Statement::Break => Ok(Flow::Break),
+            Statement::Continue => Ok(Flow::Continue),
+            Statement::Block(items) => {
+                for item in items {
+                    match self.block_item(item, locals, depth)? {
+                        Flow::Normal => {}
+                        flow => return Ok(flow),
+                    }
+                }
+                Ok(Flow::Normal)
+            }
+            // Reached in order, a label does nothing but run the statement it marks
+            Statement::Label(_, statement) => self.statement(statement, locals, depth),
+            Statement::Goto(label) => Err(format!("Runtime Error: 'goto {}' is not supported by the interpreter yet", label)),
+            Statement::Switch { .. } | Statement::Case(..) | Statement::Default(_) => {
+                Err("Runtime Error: 'switch' statements are not supported yet".to_string())
+            }
+        }
+    }
+
+    // Evaluate an expression. Arithmetic wraps on overflow like the generated code; operations
+    // whose result is undefined, like division by zero, stop the run instead.
+    fn expr(&self, expr: &Expr, locals: &mut Locals, depth: usize) -> Result<Value, String> {
+        match expr {
+            Expr::Constant(value) => Ok(Value::Int(*value)),
+            Expr::FloatConstant(value) => Ok(Value::Double(*value)),
+            Expr::Var(name) => read(locals, name).map(Value::Int),
+            Expr::Unary(op, operand) => {
+                let value = self.expr(operand, locals, depth)?;
+                Ok(match (op, value) {
+                    (UnaryOp::Negate, Value::Int(value)) => Value::Int(value.wrapping_neg()),
+                    (UnaryOp::Negate, Value::Double(value)) => Value::Double(-value),
+                    (UnaryOp::Complement, value) => Value::Int(!value.to_int()?), // Type checking only lets an 'int' through
+                    (UnaryOp::Not, value) => Value::Int(!value.is_true() as i32),
+                })
+            }
+            // The right operand only runs when the left one doesn't decide the result
+            Expr::Binary(BinaryOp::And, left, right) => {
+                let value = self.expr(left, locals, depth)?.is_true() && self.expr(right, locals, depth)?.is_true();
+                Ok(Value::Int(value as i32))
+            }
+            Expr::Binary(BinaryOp::Or, left, right) => {
+                let value = self.expr(left, locals, depth)?.is_true() || self.expr(right, locals, depth)?.is_true();
+                Ok(Value::Int(value as i32))
+            }
+            Expr::Binary(op, left, right) => {
+                let left = self.expr(left, locals, depth)?;
+                let right = self.expr(right, locals, depth)?;
+                match (left, right) {
+                    (Value::Int(a), Value::Int(b)) => int_binary(op, a, b).map(Value::Int),
+                    // An 'int' operand is converted when the other one is a 'double'
+                    (a, b) => double_binary(op, a.to_double(), b.to_double()),
+                }
+            }
+            Expr::Assign(target, value) => {
+                let name = var_name(target)?;
+                let value = self.expr(value, locals, depth)?.to_int()?; // Every variable is an 'int'
+                locals.insert(name.to_string(), value);
+                Ok(Value::Int(value))
+            }
+            Expr::PrefixInc(operand) | Expr::PrefixDec(operand) | Expr::PostfixInc(operand) | Expr::PostfixDec(operand) => {
+                let name = var_name(operand)?;
+                let old = read(locals, name)?;
+                let new = match expr {
+                    Expr::PrefixInc(_) | Expr::PostfixInc(_) => old.wrapping_add(1),
+                    _ => old.wrapping_sub(1),
+                };
+                locals.insert(name.to_string(), new);
+                Ok(Value::Int(if matches!(expr, Expr::PrefixInc(_) | Expr::PrefixDec(_)) { new } else { old }))
+            }
+            Expr::Conditional { cond, then, else_ } => {
+                let value = if self.expr(cond, locals, depth)?.is_true() {
+                    self.expr(then, locals, depth)?
+                } else {
+                    self.expr(else_, locals, depth)?
+                };
+                // An 'int' branch is converted when the other one is a 'double'
+                match value_type(expr) {
+                    Type::Double => Ok(Value::Double(value.to_double())),
+                    _ => Ok(value),
+                }
+            }
+            Expr::Comma(left, right) => {
+                self.expr(left, locals, depth)?;
+                self.expr(right, locals, depth)
+            }
+            // The operand is never evaluated, and type checking has ruled out 'void'
+            Expr::SizeOf(operand) => Ok(Value::Int(value_type(operand).size().unwrap())),
+            Expr::SizeOfType(ty) => ty.size().map(Value::Int).ok_or_else(|| format!("Runtime Error: type '{}' has no size", ty)),
+            Expr::Cast(ty, operand) => self.expr(operand, locals, depth)?.convert(ty),
+            Expr::Call { name, args } => {
+                // Every parameter is an 'int', so each argument is converted to one
+                let args = args
+                    .iter()
+                    .map(|arg| self.expr(arg, locals, depth)?.to_int())
+                    .collect::<Result<_, _>>()?;
+                self.call(name, args, depth + 1).map(Value::Int)
+            }
+            Expr::Subscript { .. } => Err("Runtime Error: array subscripts are not supported yet".to_string()),
+            Expr::Deref(_) | Expr::AddrOf(_) => Err("Runtime Error: pointers are not supported yet".to_string()),
+            Expr::Member { .. } | Expr::PtrMember { .. } => Err("Runtime Error: struct members are not supported yet".to_string()),
+        }
+    }
+}
+
+// Read a variable, which must have been given a value
+fn read(locals: &Locals, name: &str) -> Result<i32, String> {
+    match locals.get(name) {
+        Some(value) => Ok(*value),
+        None => {
+            // Report the name as written, without the suffix that made it unique
+            let source_name = name.rsplit_once('.').map_or(name, |(source_name, _)| source_name);
+            Err(format!("Runtime Error: variable '{}' is used before it is given a value", source_name))
+        }
+    }
+}
+
+// The unique name of a variable that is stored to; the parser only accepts lvalues, and
+// variables are the only ones supported so far
+fn var_name(expr: &Expr) -> Result<&str, String> {
+    match expr {
+        Expr::Var(name) => Ok(name),
+        _ => Err("Runtime Error: only variables can be assigned to yet".to_string()),
+    }
+}
+
+// Apply a binary operator to two 'int' values that have both been evaluated
+fn int_binary(op: &BinaryOp, a: i32, b: i32) -> Result<i32, String> {
+    let value = match op {
+        BinaryOp::Add => a.wrapping_add(b),
+        BinaryOp::Subtract => a.wrapping_sub(b),
+        BinaryOp::Multiply => a.wrapping_mul(b),
+        BinaryOp::Divide | BinaryOp::Remainder if b == 0 => return Err("Runtime Error: division by zero".to_string()),
+        BinaryOp::Divide => a.checked_div(b).ok_or("Runtime Error: division overflows 'int'")?,
+        BinaryOp::Remainder => a.checked_rem(b).ok_or("Runtime Error: division overflows 'int'")?,
+        BinaryOp::ShiftLeft | BinaryOp::ShiftRight if !(0..32).contains(&b) => {
+            return Err(format!("Runtime Error: shift count {} is out of range", b));
+        }
+        BinaryOp::ShiftLeft => a << b,
+        BinaryOp::ShiftRight => a >> b,
+        BinaryOp::Less => (a < b) as i32,
+        BinaryOp::LessEqual => (a <= b) as i32,
+        BinaryOp::Greater => (a > b) as i32,
+        BinaryOp::GreaterEqual => (a >= b) as i32,
+        BinaryOp::Equal => (a == b) as i32,
+        BinaryOp::NotEqual => (a != b) as i32,
+        BinaryOp::BitAnd => a & b,
+        BinaryOp::BitXor => a ^ b,
+        BinaryOp::BitOr => a | b,
+        BinaryOp::And => (a != 0 && b != 0) as i32,
+        BinaryOp::Or => (a != 0 || b != 0) as i32,
+    };
+    Ok(value)
+}
+
+// Apply a binary operator to two 'double' values; division by zero gives an infinity or NaN
+// as IEEE 754 arithmetic does, and comparisons give an 'int'
+fn double_binary(op: &BinaryOp, a: f64, b: f64) -> Result<Value, String> {
+    let value = match op {
+        BinaryOp::Add => Value::Double(a + b),
+        BinaryOp::Subtract => Value::Double(a - b),
+        BinaryOp::Multiply => Value::Double(a * b),
+        BinaryOp::Divide => Value::Double(a / b),
+        BinaryOp::Less => Value::Int((a < b) as i32),
+        BinaryOp::LessEqual => Value::Int((a <= b) as i32),
+        BinaryOp::Greater => Value::Int((a > b) as i32),
+        BinaryOp::GreaterEqual => Value::Int((a >= b) as i32),
+        BinaryOp::Equal => Value::Int((a == b) as i32),
+        BinaryOp::NotEqual => Value::Int((a != b) as i32),
+        BinaryOp::And => Value::Int((a != 0.0 && b != 0.0) as i32),
+        BinaryOp::Or => Value::Int((a != 0.0 || b != 0.0) as i32),
+        // Type checking only lets 'int' operands through to the others
+        _ => return Err(format!("Runtime Error: '{}' cannot be applied to a 'double'", op.symbol())),
+    };
+    Ok(value)
+}
@@ -0,0 +1,1193 @@
+// Lexical analysis: turns C source text into a stream of tokens tagged with their positions
+use std::fmt; // Import formatting traits for describing tokens
+
+// Define an enumeration for different types of tokens recognized by the lexer
+#[derive(Debug, Clone, PartialEq)]
+pub enum Token {
+    Identifier(String), // Represents variable/function names
+    Constant(i64), // Represents integer constants, already converted from decimal, octal, or hex
+    FloatConstant(String), // Represents floating-point constants
+    CharConstant(char), // Represents character constants like 'a'
+    StringLiteral(String), // Represents string literals like "hello"
+    LineComment(String), // Text after '//' up to the end of the line, when comments are preserved
+    BlockComment(String), // Text between '/*' and '*/', when comments are preserved
+    IntKeyword, // 'int' keyword
+    VoidKeyword, // 'void' keyword
+    ReturnKeyword, // 'return' keyword
+    IfKeyword, // 'if' keyword
+    ElseKeyword, // 'else' keyword
+    WhileKeyword, // 'while' keyword
+    ForKeyword, // 'for' keyword
+    BreakKeyword, // 'break' keyword
+    ContinueKeyword, // 'continue' keyword
+    CharKeyword, // 'char' keyword
+    ShortKeyword, // 'short' keyword
+    LongKeyword, // 'long' keyword
+    FloatKeyword, // 'float' keyword
+    DoubleKeyword, // 'double' keyword
+    SignedKeyword, // 'signed' keyword
+    UnsignedKeyword, // 'unsigned' keyword
+    StructKeyword, // 'struct' keyword
+    EnumKeyword, // 'enum' keyword
+    UnionKeyword, // 'union' keyword
+    TypedefKeyword, // 'typedef' keyword
+    StaticKeyword, // 'static' keyword
+    ExternKeyword, // 'extern' keyword
+    GotoKeyword, // 'goto' keyword
+    SwitchKeyword, // 'switch' keyword
+    CaseKeyword, // 'case' keyword
+    DefaultKeyword, // 'default' keyword
+    SizeofKeyword, // 'sizeof' keyword
+    DoKeyword, // 'do' keyword
+    OpenParenthesis, // '('
+    CloseParenthesis, // ')'
+    OpenBrace, // '{'
+    CloseBrace, // '}'
+    OpenBracket, // '['
+    CloseBracket, // ']'
+    Semicolon, // ';'
+    Comma, // ','
+    Plus, // '+'
+    Minus, // '-'
+    Asterisk, // '*'
+    Slash, // '/'
+    Percent, // '%'
+    Less, // '<'
+    Greater, // '>'
+    LessEqual, // '<='
+    GreaterEqual, // '>='
+    EqualEqual, // '=='
+    NotEqual, // '!='
+    Assign, // '='
+    LogicalAnd, // '&&'
+    LogicalOr, // '||'
+    LogicalNot, // '!'
+    Ampersand, // '&'
+    Pipe, // '|'
+    Caret, // '^'
+    Tilde, // '~'
+    Dot, // '.'
+    Arrow, // '->'
+    Question, // '?'
+    Colon, // ':'
+    ShiftLeft, // '<<'
+    ShiftRight, // '>>'
+    PlusAssign, // '+='
+    MinusAssign, // '-='
+    StarAssign, // '*='
+    SlashAssign, // '/='
+    PercentAssign, // '%='
+    Increment, // '++'
+    Decrement, // '--'
+}
+
+// Describe a token the way it appears in source, for use in error messages
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            Token::Identifier(name) => return write!(f, "identifier '{}'", name),
+            Token::Constant(value) => return write!(f, "constant '{}'", value),
+            Token::FloatConstant(value) => return write!(f, "constant '{}'", value),
+            Token::CharConstant(value) => return write!(f, "character constant {:?}", value),
+            Token::StringLiteral(value) => return write!(f, "string literal {:?}", value),
+            Token::LineComment(text) => return write!(f, "comment '//{}'", text),
+            Token::BlockComment(text) => return write!(f, "comment '/*{}*/'", text),
+            Token::IntKeyword => "int",
+            Token::VoidKeyword => "void",
+            Token::ReturnKeyword => "return",
+            Token::IfKeyword => "if",
+            Token::ElseKeyword => "else",
+            Token::WhileKeyword => "while",
+            Token::ForKeyword => "for",
+            Token::BreakKeyword => "break",
+            Token::ContinueKeyword => "continue",
+            Token::CharKeyword => "char",
+            Token::ShortKeyword => "short",
+            Token::LongKeyword => "long",
+            Token::FloatKeyword => "float",
+            Token::DoubleKeyword => "double",
+            Token::SignedKeyword => "signed",
+            Token::UnsignedKeyword => "unsigned",
+            Token::StructKeyword => "struct",
+            Token::EnumKeyword => "enum",
+            Token::UnionKeyword => "union",
+            Token::TypedefKeyword => "typedef",
+            Token::StaticKeyword => "static",
+            Token::ExternKeyword => "extern",
+            Token::GotoKeyword => "goto",
+            Token::SwitchKeyword => "switch",
+            Token::CaseKeyword => "case",
+            Token::DefaultKeyword => "default",
+            Token::SizeofKeyword => "sizeof",
+            Token::DoKeyword => "do",
+            Token::OpenParenthesis => "(",
+            Token::CloseParenthesis => ")",
+            Token::OpenBrace => "{",
+            Token::CloseBrace => "}",
+            Token::OpenBracket => "[",
+            Token::CloseBracket => "]",
+            Token::Semicolon => ";",
+            Token::Comma => ",",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Asterisk => "*",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::Less => "<",
+            Token::Greater => ">",
+            Token::LessEqual => "<=",
+            Token::GreaterEqual => ">=",
+            Token::EqualEqual => "==",
+            Token::NotEqual => "!=",
+            Token::Assign => "=",
+            Token::LogicalAnd => "&&",
+            Token::LogicalOr => "||",
+            Token::LogicalNot => "!",
+            Token::Ampersand => "&",
+            Token::Pipe => "|",
+            Token::Caret => "^",
+            Token::Tilde => "~",
+            Token::Dot => ".",
+            Token::Arrow => "->",
+            Token::Question => "?",
+            Token::Colon => ":",
+            Token::ShiftLeft => "<<",
+            Token::ShiftRight => ">>",
+            Token::PlusAssign => "+=",
+            Token::MinusAssign => "-=",
+            Token::StarAssign => "*=",
+            Token::SlashAssign => "/=",
+            Token::PercentAssign => "%=",
+            Token::Increment => "++",
+            Token::Decrement => "--",
+        };
+        write!(f, "'{}'", text)
+    }
+}
+
+// A value paired with the line and column (both starting at 1) where it begins in the source
+#[derive(Debug, Clone)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub line: usize,
+    pub col: usize,
+}
+
+// Columns between tab stops when none is given, as in most terminals and editors
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+// Settings that change how the lexer treats its input; the defaults follow standard C
+#[derive(Debug, Clone)]
+pub struct LexerOptions {
+    pub allow_nested_comments: bool, // Let '/* /* */ */' nest instead of ending at the first '*/'
+    pub preserve_comments: bool, // Emit comments as tokens instead of discarding them
+    pub tab_width: usize, // A tab moves the column to the next multiple of this, plus one
+}
+
+impl Default for LexerOptions {
+    fn default() -> Self {
+        LexerOptions { allow_nested_comments: false, preserve_comments: false, tab_width: DEFAULT_TAB_WIDTH }
+    }
+}
+
+// Character iterator over the source that keeps track of the current line and column.
+// It scans the source bytes by index, decoding UTF-8 only for non-ASCII characters, so
+// cloning it for lookahead is just a copy of a few fields.
+// Windows '\r\n' and lone '\r' line endings are both seen as a single '\n'.
+#[derive(Clone)]
+struct Cursor<'a> {
+    source: &'a str, // The whole source text
+    pos: usize, // Byte offset of the next character
+    current: Option<char>, // The next character, already decoded, or `None` at the end
+    line: usize, // Line of the next character
+    col: usize, // Column of the next character, with tabs expanded to the next tab stop
+    tab_width: usize, // Columns between tab stops
+}
+
+impl<'a> Cursor<'a> {
+    fn new(source: &'a str, tab_width: usize) -> Self {
+        // A width of 0 would never reach a tab stop, so a tab counts as one column there
+        Cursor { source, pos: 0, current: decode(source, 0), line: 1, col: 1, tab_width: tab_width.max(1) }
+    }
+
+    // Look at the next character without consuming it
+    fn peek(&mut self) -> Option<&char> {
+        self.current.as_ref()
+    }
+}
+
+// Decode the character starting at byte `pos`, seeing a '\r' as the line break it starts
+fn decode(source: &str, pos: usize) -> Option<char> {
+    let byte = *source.as_bytes().get(pos)?;
+    let c = if byte.is_ascii() { byte as char } else { source[pos..].chars().next()? };
+    Some(if c == '\r' { '\n' } else { c })
+}
+
+impl Iterator for Cursor<'_> {
+    type Item = char;
+
+    // Consume the next character, moving to the start of the next line after a newline
+    fn next(&mut self) -> Option<char> {
+        let c = self.current?;
+        let bytes = self.source.as_bytes();
+        if bytes[self.pos] == b'\r' {
+            self.pos += 1;
+            if bytes.get(self.pos) == Some(&b'\n') {
+                self.pos += 1; // Fold '\r\n' into one line break
+            }
+        } else {
+            self.pos += c.len_utf8();
+        }
+        self.current = decode(self.source, self.pos);
+        match c {
+            '\n' => {
+                self.line += 1;
+                self.col = 1;
+            }
+            '\t' => self.col += self.tab_width - (self.col - 1) % self.tab_width, // Jump to the next tab stop
+            _ => self.col += 1,
+        }
+        Some(c)
+    }
+}
+
+// Pick between a one-character operator and its two-character forms by peeking at the
+// character after the one already consumed, e.g. '<' versus '<=' and '<<'
+fn lex_operator(chars: &mut Cursor, single: Token, doubles: &[(char, Token)]) -> Token {
+    if let Some(&next) = chars.peek() {
+        for (second, token) in doubles {
+            if next == *second {
+                chars.next(); // Consume the second character of the operator
+                return token.clone();
+            }
+        }
+    }
+    single // No two-character form matched, so it's the single-character operator
+}
+
+// Append every character accepted by `is_digit` to `num`, stopping at the first one that isn't
+fn consume_digits(chars: &mut Cursor, num: &mut String, is_digit: impl Fn(char) -> bool) {
+    while let Some(&d) = chars.peek() {
+        if is_digit(d) {
+            num.push(d); // Add digit to the number string
+            chars.next(); // Consume the character
+        } else {
+            break; // Break if the character is no longer a digit
+        }
+    }
+}
+
+// Check an integer suffix: at most one 'u' and at most two contiguous 'l's, in any case
+fn is_valid_int_suffix(suffix: &str) -> bool {
+    let lower = suffix.to_ascii_lowercase();
+    let u_count = lower.matches('u').count();
+    let l_run = lower.trim_matches('u'); // The 'l's must sit together on one side of the 'u'
+    u_count <= 1 && l_run.len() <= 2 && l_run.chars().all(|ch| ch == 'l')
+}
+
+// Lex a numeric constant starting at a digit or at a '.' followed by a digit.
+// Integers may be decimal, octal, or hexadecimal with a 'u'/'l' suffix; anything with a
+// fraction or an exponent becomes a floating-point constant.
+fn lex_number(chars: &mut Cursor) -> Result<Token, String> {
+    let first = chars.next().unwrap(); // Consume the leading digit or dot
+    let mut num = first.to_string(); // Create a string to hold numeric constant
+
+    if first == '0' && matches!(chars.peek(), Some('x') | Some('X')) {
+        num.push(chars.next().unwrap()); // Add the 'x' of the hexadecimal prefix
+        consume_digits(chars, &mut num, |d| d.is_ascii_hexdigit()); // Read hexadecimal digits
+        if num.len() == 2 {
+            return Err(format!("Lexical Error: Hexadecimal constant '{}' has no digits", num));
+        }
+        return lex_int_suffix(chars, num);
+    }
+
+    consume_digits(chars, &mut num, |d| d.is_ascii_digit()); // Read the integer part (or the fraction after '.')
+    let mut is_float = first == '.';
+    if !is_float && chars.peek() == Some(&'.') {
+        num.push('.'); // Add the decimal point
+        chars.next(); // Consume the character
+        consume_digits(chars, &mut num, |d| d.is_ascii_digit()); // Read the fraction, which may be empty as in '2.'
+        is_float = true;
+    }
+    if matches!(chars.peek(), Some('e') | Some('E')) {
+        num.push(chars.next().unwrap()); // Add the exponent marker
+        if let Some(&sign) = chars.peek() {
+            if sign == '+' || sign == '-' {
+                num.push(sign); // Add the exponent sign
+                chars.next(); // Consume the character
+            }
+        }
+        let before = num.len();
+        consume_digits(chars, &mut num, |d| d.is_ascii_digit()); // Read the exponent digits
+        if num.len() == before {
+            return Err(format!("Lexical Error: Exponent has no digits in floating-point constant '{}'", num));
+        }
+        is_float = true;
+    }
+
+    if is_float {
+        if let Some(&next) = chars.peek() {
+            // A second '.' as in '1.2.3' can't continue the constant
+            if next == '.' {
+                consume_digits(chars, &mut num, |d| d.is_ascii_digit() || d == '.');
+                return Err(format!("Lexical Error: Invalid floating-point constant '{}'", num));
+            }
+            if next.is_alphanumeric() || next == '_' {
+                consume_digits(chars, &mut num, |d| d.is_alphanumeric() || d == '_'); // Take the whole bad lexeme
+                return Err(format!("Lexical Error: Identifiers cannot start with a number: '{}'", num));
+            }
+        }
+        return Ok(Token::FloatConstant(num));
+    }
+
+    // A leading zero makes the constant octal, which only allows digits 0-7
+    if first == '0' {
+        if let Some(bad) = num.chars().find(|&d| d == '8' || d == '9') {
+            return Err(format!("Lexical Error: Invalid digit '{}' in octal constant '{}'", bad, num));
+        }
+    }
+    lex_int_suffix(chars, num)
+}
+
+// Read an optional integer suffix such as 'u', 'L', or 'ULL' and build the constant token
+fn lex_int_suffix(chars: &mut Cursor, num: String) -> Result<Token, String> {
+    let mut suffix = String::new();
+    consume_digits(chars, &mut suffix, |d| matches!(d, 'u' | 'U' | 'l' | 'L'));
+    // Ensure the number is not followed by an identifier
+    if let Some(&next) = chars.peek() {
+        if next.is_alphanumeric() || next == '_' {
+            let has_suffix = !suffix.is_empty();
+            consume_digits(chars, &mut suffix, |d| d.is_alphanumeric() || d == '_'); // Take the whole bad lexeme
+            if has_suffix {
+                return Err(format!("Lexical Error: Invalid suffix '{}' on integer constant '{}'", suffix, num));
+            }
+            return Err(format!("Lexical Error: Identifiers cannot start with a number: '{}{}'", num, suffix));
+        }
+    }
+    if !is_valid_int_suffix(&suffix) {
+        return Err(format!("Lexical Error: Invalid suffix '{}' on integer constant '{}'", suffix, num));
+    }
+    match int_constant_value(&num) {
+        Some(value) => Ok(Token::Constant(value)),
+        None => Err(format!("Lexical Error: integer constant out of range: '{}{}'", num, suffix)),
+    }
+}
+
+// Convert the digits of an integer constant (decimal, octal, or hex) into its value,
+// or `None` if it doesn't fit in an i64
+fn int_constant_value(digits: &str) -> Option<i64> {
+    if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        i64::from_str_radix(&digits[1..], 8).ok()
+    } else {
+        digits.parse().ok()
+    }
+}
+
+// Decode the escape sequence following a backslash, which has already been consumed
+fn lex_escape(chars: &mut Cursor) -> Result<char, String> {
+    match chars.next() {
+        Some('n') => Ok('\n'), // Newline
+        Some('t') => Ok('\t'), // Horizontal tab
+        Some('r') => Ok('\r'), // Carriage return
+        Some('0') => Ok('\0'), // Null character
+        Some('\\') => Ok('\\'), // Backslash
+        Some('\'') => Ok('\''), // Single quote
+        Some('"') => Ok('"'), // Double quote
+        Some(other) => Err(format!("Lexical Error: Unknown escape sequence '\\{}'", other)),
+        None => Err("Lexical Error: Unterminated escape sequence at end of file".to_string()),
+    }
+}
+
+// Lex a single-quoted character literal such as 'a' or '\n'
+fn lex_char_literal(chars: &mut Cursor) -> Result<Token, String> {
+    chars.next(); // Consume the opening quote
+    let value = match chars.next() {
+        Some('\\') => lex_escape(chars)?, // Escape sequence
+        Some('\'') => return Err("Lexical Error: Empty character literal ''".to_string()),
+        Some('\n') | None => return Err("Lexical Error: Unterminated character literal".to_string()),
+        Some(ch) => ch, // Plain character
+    };
+    match chars.next() {
+        Some('\'') => Ok(Token::CharConstant(value)), // Closing quote
+        Some('\n') | None => Err("Lexical Error: Unterminated character literal".to_string()),
+        Some(_) => Err("Lexical Error: Character literal contains more than one character".to_string()),
+    }
+}
+
+// Lex a double-quoted string literal, decoding escape sequences into their real characters
+fn lex_string_literal(chars: &mut Cursor) -> Result<Token, String> {
+    chars.next(); // Consume the opening quote
+    let mut text = String::new(); // Create a string to hold the decoded contents
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(Token::StringLiteral(text)), // Closing quote
+            Some('\\') => {
+                if chars.peek() == Some(&'\n') {
+                    chars.next(); // A backslash-newline continues the string on the next line
+                } else {
+                    text.push(lex_escape(chars)?); // Decode the escape sequence
+                }
+            }
+            Some('\n') => return Err("Lexical Error: Newline in string literal".to_string()),
+            Some(ch) => text.push(ch), // Plain character
+            None => return Err("Lexical Error: Unterminated string literal".to_string()),
+        }
+    }
+}
+
+// Lex the token starting at the next character, or return `None` after skipping
+// whitespace or a comment that is not being preserved. Errors are reported without a
+// position; the caller adds it.
+fn lex_token(chars: &mut Cursor, options: &LexerOptions) -> Result<Option<Token>, String> {
+    let c = *chars.peek().unwrap(); // The caller guarantees there is a character left
+    let token = match c {
+        ' ' | '\n' | '\t' => {
+            chars.next(); // Ignore whitespace characters
+            return Ok(None);
+        }
+        '(' => {
+            chars.next(); // Consume the character
+            Token::OpenParenthesis // Recognize opening parenthesis
+        }
+        ')' => {
+            chars.next(); // Consume the character
+            Token::CloseParenthesis // Recognize closing parenthesis
+        }
+        '{' => {
+            chars.next(); // Consume the character
+            Token::OpenBrace // Recognize opening brace
+        }
+        '}' => {
+            chars.next(); // Consume the character
+            Token::CloseBrace // Recognize closing brace
+        }
+        '[' => {
+            chars.next(); // Consume the character
+            Token::OpenBracket // Recognize opening bracket
+        }
+        ']' => {
+            chars.next(); // Consume the character
+            Token::CloseBracket // Recognize closing bracket
+        }
+        ';' => {
+            chars.next(); // Consume the character
+            Token::Semicolon // Recognize semicolon
+        }
+        ',' => {
+            chars.next(); // Consume the character
+            Token::Comma // Recognize comma
+        }
+        '+' => {
+            chars.next(); // Consume '+'
+            lex_operator(chars, Token::Plus, &[('=', Token::PlusAssign), ('+', Token::Increment)]) // Recognize '+', '+=', or '++'
+        }
+        '-' => {
+            chars.next(); // Consume '-'
+            // Recognize '-', '-=', '->', or '--'
+            lex_operator(chars, Token::Minus, &[('=', Token::MinusAssign), ('>', Token::Arrow), ('-', Token::Decrement)])
+        }
+        '*' => {
+            chars.next(); // Consume '*'
+            lex_operator(chars, Token::Asterisk, &[('=', Token::StarAssign)]) // Recognize '*' or '*='
+        }
+        '%' => {
+            chars.next(); // Consume '%'
+            lex_operator(chars, Token::Percent, &[('=', Token::PercentAssign)]) // Recognize '%' or '%='
+        }
+        '<' => {
+            chars.next(); // Consume '<'
+            lex_operator(chars, Token::Less, &[('=', Token::LessEqual), ('<', Token::ShiftLeft)])
+        }
+        '>' => {
+            chars.next(); // Consume '>'
+            lex_operator(chars, Token::Greater, &[('=', Token::GreaterEqual), ('>', Token::ShiftRight)])
+        }
+        '=' => {
+            chars.next(); // Consume '='
+            // A lone '=' (including at end of file) is assignment
+            lex_operator(chars, Token::Assign, &[('=', Token::EqualEqual)])
+        }
+        '!' => {
+            chars.next(); // Consume '!'
+            lex_operator(chars, Token::LogicalNot, &[('=', Token::NotEqual)])
+        }
+        '&' => {
+            chars.next(); // Consume '&'
+            lex_operator(chars, Token::Ampersand, &[('&', Token::LogicalAnd)])
+        }
+        '|' => {
+            chars.next(); // Consume '|'
+            lex_operator(chars, Token::Pipe, &[('|', Token::LogicalOr)])
+        }
+        '^' => {
+            chars.next(); // Consume the character
+            Token::Caret // Recognize bitwise xor
+        }
+        '~' => {
+            chars.next(); // Consume the character
+            Token::Tilde // Recognize bitwise complement
+        }
+        '?' => {
+            chars.next(); // Consume the character
+            Token::Question // Recognize the conditional operator's '?'
+        }
+        ':' => {
+            chars.next(); // Consume the character
+            Token::Colon // Recognize the conditional operator's ':'
+        }
+        ///////////////////////
+        '0'..='9' => {
+            lex_number(chars)? // Store integer or floating-point constants
+        }
+        '.' => {
+            // A dot only starts a constant when a digit follows it, as in '.5'
+            let mut ahead = chars.clone();
+            ahead.next();
+            if matches!(ahead.peek(), Some('0'..='9')) {
+                lex_number(chars)? // Store floating-point constants like '.5'
+            } else {
+                chars.next(); // Consume the character
+                Token::Dot // Recognize member access
+            }
+        }
+        '\'' => {
+            lex_char_literal(chars)? // Store character constants
+        }
+        '"' => {
+            lex_string_literal(chars)? // Store string literals
+        }
+        //////////////////////////////
+        'a'..='z' | 'A'..='Z' | '_' => {
+            let mut ident = String::new(); // Create a string to hold identifier
+            while let Some(&d) = chars.peek() {
+                if d.is_alphanumeric() || d == '_' {
+                    ident.push(d); // Add character to the identifier string
+                    chars.next(); // Consume the character
+                } else {
+                    break; // Break if the character is no longer part of an identifier
+                }
+            }
+
+            // Check if the identifier starts with a number
+            if ident.chars().next().unwrap().is_numeric() {
+                return Err(format!("Lexical Error: Identifiers cannot start with a number: '{}'", ident));
+            }
+
+            // Check if the identifier is just an underscore
+            if ident == "_" {
+                return Err("Lexical Error: Standalone underscore '_' is not a valid identifier".to_string());
+            }
+
+            // Check if the identifier contains invalid characters
+            if ident.chars().any(|ch| !(ch.is_alphanumeric() || ch == '_')) {
+                return Err(format!("Lexical Error: Invalid identifier '{}'", ident));
+            }
+
+            // Match known keywords or treat as a generic identifier
+            match ident.as_str() {
+                "int" => Token::IntKeyword, // Recognize 'int' keyword
+                "void" => Token::VoidKeyword, // Recognize 'void' keyword
+                "return" => Token::ReturnKeyword, // Recognize 'return' keyword
+                "if" => Token::IfKeyword, // Recognize 'if' keyword
+                "else" => Token::ElseKeyword, // Recognize 'else' keyword
+                "while" => Token::WhileKeyword, // Recognize 'while' keyword
+                "for" => Token::ForKeyword, // Recognize 'for' keyword
+                "break" => Token::BreakKeyword, // Recognize 'break' keyword
+                "continue" => Token::ContinueKeyword, // Recognize 'continue' keyword
+                "char" => Token::CharKeyword, // Recognize 'char' keyword
+                "short" => Token::ShortKeyword, // Recognize 'short' keyword
+                "long" => Token::LongKeyword, // Recognize 'long' keyword
+                "float" => Token::FloatKeyword, // Recognize 'float' keyword
+                "double" => Token::DoubleKeyword, // Recognize 'double' keyword
+                "signed" => Token::SignedKeyword, // Recognize 'signed' keyword
+                "unsigned" => Token::UnsignedKeyword, // Recognize 'unsigned' keyword
+                "struct" => Token::StructKeyword, // Recognize 'struct' keyword
+                "enum" => Token::EnumKeyword, // Recognize 'enum' keyword
+                "union" => Token::UnionKeyword, // Recognize 'union' keyword
+                "typedef" => Token::TypedefKeyword, // Recognize 'typedef' keyword
+                "static" => Token::StaticKeyword, // Recognize 'static' keyword
+                "extern" => Token::ExternKeyword, // Recognize 'extern' keyword
+                "goto" => Token::GotoKeyword, // Recognize 'goto' keyword
+                "switch" => Token::SwitchKeyword, // Recognize 'switch' keyword
+                "case" => Token::CaseKeyword, // Recognize 'case' keyword
+                "default" => Token::DefaultKeyword, // Recognize 'default' keyword
+                "sizeof" => Token::SizeofKeyword, // Recognize 'sizeof' keyword
+                "do" => Token::DoKeyword, // Recognize 'do' keyword
+                _ => Token::Identifier(ident), // Otherwise, it's a generic identifier
+            }
+        }
+        '/' => {
+            chars.next(); // Consume the '/' character
+            match chars.peek() {
+                Some('/') => {
+                    chars.next(); // Consume the second '/'
+                    let mut text = String::new(); // Comment text without the leading '//'
+                    while let Some(&c) = chars.peek() {
+                        if c == '\n' { // End of single-line comment
+                            break;
+                        }
+                        text.push(c);
+                        chars.next(); // Consume the character
+                    }
+                    return Ok(options.preserve_comments.then_some(Token::LineComment(text)));
+                }
+                Some('*') => {
+                    chars.next(); // Consume '*'
+                    let mut depth = 1; // Number of comments currently open
+                    let mut text = String::new(); // Comment text without the outer delimiters
+                    while let Some(ch) = chars.next() {
+                        if ch == '*' && chars.peek() == Some(&'/') {
+                            chars.next(); // Consume '/'
+                            depth -= 1;
+                            if depth == 0 {
+                                // End of multi-line comment
+                                return Ok(options.preserve_comments.then_some(Token::BlockComment(text)));
+                            }
+                            text.push_str("*/"); // A nested comment closes inside this one
+                        } else if ch == '/' && chars.peek() == Some(&'*') && options.allow_nested_comments {
+                            chars.next(); // Consume '*'
+                            depth += 1; // A nested comment opens inside this one
+                            text.push_str("/*");
+                        } else {
+                            text.push(ch);
+                        }
+                    }
+                    // The caller reports the position where the comment started
+                    return Err("Lexical Error: Unterminated block comment".to_string());
+                }
+                Some('=') => {
+                    chars.next(); // Consume '='
+                    Token::SlashAssign // Recognize '/='
+                }
+                _ => Token::Slash, // Not a comment, so it's the division operator
+            }
+        }
+        _ => {
+            return Err(format!("Lexical Error: Invalid character '{}'", c)); // Handle invalid characters
+        }
+    };
+    Ok(Some(token))
+}
+
+// Perform lexical analysis on C source text using the standard C rules
+pub fn tokenize(source: &str) -> Result<Vec<Spanned<Token>>, Vec<Spanned<String>>> {
+    tokenize_with_options(source, &LexerOptions::default())
+}
+
+// Lexer that produces tokens one at a time as they are asked for, so a caller can stop
+// early without lexing the rest of the file. After an error it resumes with the next
+// lexeme, so iterating to the end sees every error in the file.
+pub struct Lexer<'a> {
+    chars: Cursor<'a>, // Walk the file content while tracking line and column
+    options: LexerOptions,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(source: &'a str, options: &LexerOptions) -> Self {
+        Lexer { chars: Cursor::new(source, options.tab_width), options: options.clone() }
+    }
+}
+
+impl Iterator for Lexer<'_> {
+    type Item = Result<Spanned<Token>, Spanned<String>>; // An error is its rendered message at the lexeme's position
+
+    // Lex the next token with its starting position, skipping whitespace and comments
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.chars.peek().is_some() {
+            let (line, col) = (self.chars.line, self.chars.col); // Remember where this token starts
+            match lex_token(&mut self.chars, &self.options) {
+                Ok(Some(token)) => return Some(Ok(Spanned { value: token, line, col })),
+                Ok(None) => {} // Whitespace or comment, nothing to record
+                Err(e) => {
+                    // Skip the offending character if the failed lexeme consumed nothing
+                    if (self.chars.line, self.chars.col) == (line, col) {
+                        self.chars.next();
+                    }
+                    let message = format!("{} at line {}, column {}", e, line, col);
+                    let value = format_diagnostic(self.chars.source, line, col, &message, self.chars.tab_width); // Point the error at the token
+                    return Some(Err(Spanned { value, line, col }));
+                }
+            }
+        }
+        None
+    }
+}
+
+// Render an error message followed by the source line it refers to and a '^' under the
+// given column, which counts from 1 like the positions on tokens. Tabs in the line are
+// expanded to spaces at the same tab stops the columns were counted with, so the caret
+// lines up however wide the terminal draws a tab.
+pub fn format_diagnostic(source: &str, line: usize, col: usize, msg: &str, tab_width: usize) -> String {
+    let tab_width = tab_width.max(1);
+    let mut text = String::new();
+    let mut width = 0; // Columns taken by the text so far
+    for c in source_line(source, line).chars() {
+        if c == '\t' {
+            let spaces = tab_width - width % tab_width;
+            text.push_str(&" ".repeat(spaces));
+            width += spaces;
+        } else {
+            text.push(c);
+            width += 1;
+        }
+    }
+    format!("{}\n  {}\n  {}^", msg, text, " ".repeat(col.saturating_sub(1)))
+}
+
+// Find the text of a 1-based line, splitting at the same line breaks the cursor does
+fn source_line(source: &str, line: usize) -> &str {
+    let bytes = source.as_bytes();
+    let mut start = 0;
+    for _ in 1..line {
+        match bytes[start..].iter().position(|&b| b == b'\n' || b == b'\r') {
+            Some(offset) => {
+                start += offset + 1;
+                if bytes[start - 1] == b'\r' && bytes.get(start) == Some(&b'\n') {
+                    start += 1; // '\r\n' is one line break
+                }
+            }
+            None => return "", // The line is past the end of the source
+        }
+    }
+    let end = bytes[start..].iter().position(|&b| b == b'\n' || b == b'\r').map_or(source.len(), |offset| start + offset);
+    &source[start..end]
+}
+
+// Perform lexical analysis on C source text, returning each token with its starting position.
+// Lexing continues past an invalid lexeme so that every error in the file is reported at once.
+pub fn tokenize_with_options(source: &str, options: &LexerOptions) -> Result<Vec<Spanned<Token>>, Vec<Spanned<String>>> {
+    // C averages well under one token per eight bytes, so this rarely has to grow more than once
+    let mut tokens = Vec::with_capacity(source.len() / 8);
+    let mut errors = Vec::new(); // Every lexical error found so far, in source order
+    for result in Lexer::new(source, options) {
+        match result {
+            Ok(token) => tokens.push(token),
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok(tokens) // Hand the token stream back to the caller
+}
+
+// Join the messages of several lexical errors into one, one error per line
+pub fn join_errors(errors: &[Spanned<String>]) -> String {
+    errors.iter().map(|error| error.value.as_str()).collect::<Vec<_>>().join("\n")
+}
+
+// Quote a string for JSON, escaping the characters JSON does not allow raw
+fn json_string(text: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Serialize tokens as a JSON array of {"kind", "value" (for tokens that carry text), "line", "col"} objects
+pub fn tokens_to_json(tokens: &[Spanned<Token>]) -> String {
+    let entries: Vec<String> = tokens
+        .iter()
+        .map(|token| {
+            let debug = format!("{:?}", token.value);
+            let kind = debug.split('(').next().unwrap_or(&debug); // The variant name without its payload
+            let value = match &token.value {
+                Token::Constant(value) => Some(value.to_string()),
+                Token::Identifier(text)
+                | Token::FloatConstant(text)
+                | Token::StringLiteral(text)
+                | Token::LineComment(text)
+                | Token::BlockComment(text) => Some(text.clone()),
+                Token::CharConstant(c) => Some(c.to_string()),
+                _ => None,
+            };
+            match value {
+                Some(value) => format!(
+                    "{{\"kind\":{},\"value\":{},\"line\":{},\"col\":{}}}",
+                    json_string(kind), json_string(&value), token.line, token.col
+                ),
+                None => format!("{{\"kind\":{},\"line\":{},\"col\":{}}}", json_string(kind), token.line, token.col),
+            }
+        })
+        .collect();
+    format!("[{}]", entries.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Token::*;
+    use super::*;
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+
+    // Lex a source and keep only the tokens, dropping their positions
+    fn lex(source: &str) -> Vec<Token> {
+        tokenize(source).unwrap().into_iter().map(|token| token.value).collect()
+    }
+
+    // Lex a source with the given options and keep only the tokens
+    fn lex_with(source: &str, options: &LexerOptions) -> Vec<Token> {
+        tokenize_with_options(source, options).unwrap().into_iter().map(|token| token.value).collect()
+    }
+
+    // Lex a source that should fail and return its first error message
+    fn lex_error(source: &str) -> String {
+        tokenize(source).unwrap_err().remove(0).value
+    }
+
+    fn ident(name: &str) -> Token {
+        Identifier(name.to_string())
+    }
+
+    #[test]
+    fn arithmetic_operators() {
+        assert_eq!(
+            lex("1 + 2 * 3 - 4 / 5 % 6"),
+            vec![Constant(1), Plus, Constant(2), Asterisk, Constant(3), Minus, Constant(4), Slash, Constant(5), Percent, Constant(6)]
+        );
+    }
+
+    #[test]
+    fn relational_and_equality_operators() {
+        assert_eq!(
+            lex("a <= b >= c == d != e < f > g"),
+            vec![
+                ident("a"), LessEqual, ident("b"), GreaterEqual, ident("c"), EqualEqual, ident("d"),
+                NotEqual, ident("e"), Less, ident("f"), Greater, ident("g"),
+            ]
+        );
+    }
+
+    #[test]
+    fn assignment_is_distinct_from_equality() {
+        assert_eq!(lex("x = y == z"), vec![ident("x"), Assign, ident("y"), EqualEqual, ident("z")]);
+    }
+
+    #[test]
+    fn trailing_assign_at_end_of_file() {
+        assert_eq!(lex("x ="), vec![ident("x"), Assign]);
+    }
+
+    #[test]
+    fn logical_operators() {
+        assert_eq!(lex("!a && b || !c"), vec![LogicalNot, ident("a"), LogicalAnd, ident("b"), LogicalOr, LogicalNot, ident("c")]);
+    }
+
+    #[test]
+    fn bitwise_and_shift_operators() {
+        assert_eq!(
+            lex("a & b | c ^ ~d << 2 >> 1"),
+            vec![ident("a"), Ampersand, ident("b"), Pipe, ident("c"), Caret, Tilde, ident("d"), ShiftLeft, Constant(2), ShiftRight, Constant(1)]
+        );
+    }
+
+    #[test]
+    fn compound_assignment_operators() {
+        assert_eq!(
+            lex("a += b -= c *= d /= e %= f"),
+            vec![
+                ident("a"), PlusAssign, ident("b"), MinusAssign, ident("c"), StarAssign, ident("d"),
+                SlashAssign, ident("e"), PercentAssign, ident("f"),
+            ]
+        );
+    }
+
+    #[test]
+    fn slash_assign_still_leaves_comments_alone() {
+        assert_eq!(lex("a /= b // c /= d\n/* e */ f"), vec![ident("a"), SlashAssign, ident("b"), ident("f")]);
+    }
+
+    #[test]
+    fn hexadecimal_and_octal_constants() {
+        assert_eq!(lex("0x1f 0XFF 0755"), vec![Constant(31), Constant(255), Constant(493)]);
+    }
+
+    #[test]
+    fn hexadecimal_prefix_without_digits_is_rejected() {
+        assert!(lex_error("0x").contains("Hexadecimal constant '0x' has no digits"));
+    }
+
+    #[test]
+    fn octal_constant_with_eight_or_nine_is_rejected() {
+        assert!(lex_error("0789").contains("Invalid digit '8' in octal constant '0789'"));
+    }
+
+    #[test]
+    fn integer_suffixes() {
+        for suffix in ["u", "U", "l", "L", "ul", "lu", "ll", "LL", "ull", "llu", "uLL", "LLu"] {
+            assert_eq!(lex(&format!("10{}", suffix)), vec![Constant(10)], "suffix '{}'", suffix);
+        }
+    }
+
+    #[test]
+    fn invalid_integer_suffix_is_rejected() {
+        assert!(lex_error("10ux").contains("Invalid suffix 'ux' on integer constant '10'"));
+        assert!(lex_error("10lul").contains("Invalid suffix 'lul'"));
+    }
+
+    #[test]
+    fn floating_point_constants() {
+        assert_eq!(
+            lex("3.14 .5 2. 1e10 2.5e-3"),
+            vec![
+                FloatConstant("3.14".to_string()), FloatConstant(".5".to_string()), FloatConstant("2.".to_string()),
+                FloatConstant("1e10".to_string()), FloatConstant("2.5e-3".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn float_with_two_dots_is_rejected() {
+        assert!(lex_error("1.2.3").contains("Invalid floating-point constant '1.2.3'"));
+    }
+
+    #[test]
+    fn character_constants_and_escapes() {
+        assert_eq!(
+            lex(r#"'a' '\n' '\t' '\r' '\0' '\\' '\'' '\"'"#),
+            vec![
+                CharConstant('a'), CharConstant('\n'), CharConstant('\t'), CharConstant('\r'),
+                CharConstant('\0'), CharConstant('\\'), CharConstant('\''), CharConstant('"'),
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_and_empty_character_constants_are_rejected() {
+        assert!(lex_error("'a").contains("Unterminated character literal"));
+        assert!(lex_error("''").contains("Empty character literal"));
+        assert!(lex_error("'ab'").contains("more than one character"));
+        assert!(lex_error(r"'\q'").contains("Unknown escape sequence '\\q'"));
+    }
+
+    #[test]
+    fn string_literal_escapes_are_decoded() {
+        assert_eq!(lex(r#""hello\nworld""#), vec![StringLiteral("hello\nworld".to_string())]);
+        assert_eq!(lex(r#""say \"hi\"\t\\""#), vec![StringLiteral("say \"hi\"\t\\".to_string())]);
+    }
+
+    #[test]
+    fn unterminated_string_literal_is_rejected() {
+        assert!(lex_error("\"hello").contains("Unterminated string literal"));
+        assert!(lex_error("\"hello\nworld\"").contains("Newline in string literal"));
+    }
+
+    #[test]
+    fn tokens_carry_their_line_and_column() {
+        let tokens = tokenize("int main(void) {\n\n    return 0;\n}").unwrap();
+        let ret = &tokens[6];
+        assert_eq!(ret.value, ReturnKeyword);
+        assert_eq!((ret.line, ret.col), (3, 5));
+    }
+
+    #[test]
+    fn lexical_errors_report_their_line() {
+        let error = lex_error("int main(void) {\n    int x;\n    x = 1;\n    x = @;\n}");
+        assert!(error.contains("Invalid character '@' at line 4, column 9"), "{}", error);
+    }
+
+    #[test]
+    fn unterminated_block_comment_points_at_its_start() {
+        let error = lex_error("int main() { /* oops");
+        assert!(error.contains("Unterminated block comment at line 1, column 14"), "{}", error);
+    }
+
+    #[test]
+    fn nested_comments_only_nest_when_enabled() {
+        let source = "/* a /* b */ c */ x";
+        assert_eq!(lex(source), vec![ident("c"), Asterisk, Slash, ident("x")]);
+        let nested = LexerOptions { allow_nested_comments: true, ..LexerOptions::default() };
+        assert_eq!(lex_with(source, &nested), vec![ident("x")]);
+    }
+
+    #[test]
+    fn every_lexical_error_is_reported() {
+        let errors = tokenize("int @ x $ y ` z;").unwrap_err();
+        let characters: Vec<&str> = errors.iter().map(|error| error.value.lines().next().unwrap()).collect();
+        assert_eq!(
+            characters,
+            vec![
+                "Lexical Error: Invalid character '@' at line 1, column 5",
+                "Lexical Error: Invalid character '$' at line 1, column 9",
+                "Lexical Error: Invalid character '`' at line 1, column 13",
+            ]
+        );
+    }
+
+    #[test]
+    fn comments_are_preserved_when_asked() {
+        let preserve = LexerOptions { preserve_comments: true, ..LexerOptions::default() };
+        assert_eq!(
+            lex_with("// hi\n/* bye */", &preserve),
+            vec![LineComment(" hi".to_string()), BlockComment(" bye ".to_string())]
+        );
+        assert!(lex("// hi\n/* bye */").is_empty());
+    }
+
+    #[test]
+    fn crlf_line_endings_match_lf() {
+        let lf = tokenize("int main(void)\n{\n\treturn 0;\n}\n").unwrap();
+        let crlf = tokenize("int main(void)\r\n{\r\n\treturn 0;\r\n}\r\n").unwrap();
+        let positions = |tokens: &[Spanned<Token>]| tokens.iter().map(|t| (t.value.clone(), t.line, t.col)).collect::<Vec<_>>();
+        assert_eq!(positions(&lf), positions(&crlf));
+        assert_eq!(crlf.last().unwrap().line, 4);
+    }
+
+    #[test]
+    fn number_followed_by_letters_reports_the_whole_lexeme() {
+        assert!(lex_error("123abc").contains("Identifiers cannot start with a number: '123abc'"));
+    }
+
+    #[test]
+    fn type_keywords() {
+        assert_eq!(
+            lex("char short long float double signed unsigned"),
+            vec![CharKeyword, ShortKeyword, LongKeyword, FloatKeyword, DoubleKeyword, SignedKeyword, UnsignedKeyword]
+        );
+        assert_eq!(lex("unsigned unsignedness"), vec![UnsignedKeyword, ident("unsignedness")]);
+    }
+
+    #[test]
+    fn aggregate_keywords_and_member_access() {
+        assert_eq!(lex("struct Point"), vec![StructKeyword, ident("Point")]);
+        assert_eq!(lex("enum union typedef"), vec![EnumKeyword, UnionKeyword, TypedefKeyword]);
+        assert_eq!(lex("p->x"), vec![ident("p"), Arrow, ident("x")]);
+        assert_eq!(lex("p.x - -y"), vec![ident("p"), Dot, ident("x"), Minus, Minus, ident("y")]);
+    }
+
+    #[test]
+    fn commas_and_brackets() {
+        assert_eq!(
+            lex("a[0], b[1]"),
+            vec![ident("a"), OpenBracket, Constant(0), CloseBracket, Comma, ident("b"), OpenBracket, Constant(1), CloseBracket]
+        );
+    }
+
+    #[test]
+    fn integer_constants_are_lexed_to_their_value() {
+        assert_eq!(lex("42"), vec![Constant(42)]);
+        assert_eq!(lex("0x2A"), vec![Constant(42)]);
+    }
+
+    #[test]
+    fn leading_zero_constants_are_octal() {
+        assert_eq!(lex("007"), lex("7"));
+        assert_eq!(lex("052"), vec![Constant(42)]);
+    }
+
+    #[test]
+    fn overflowing_constants_are_rejected() {
+        assert!(lex_error("99999999999999999999").contains("Lexical Error: integer constant out of range"));
+    }
+
+    // Counts the large allocations made on each thread, so a test can see how often the
+    // token vector is allocated while other tests run alongside it. Lexemes are far smaller.
+    struct CountingAllocator;
+
+    const LARGE: usize = 4096;
+
+    thread_local! {
+        static LARGE_ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    fn count_allocation(size: usize) {
+        if size >= LARGE {
+            let _ = LARGE_ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        }
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count_allocation(layout.size());
+            unsafe { System.alloc(layout) }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            unsafe { System.dealloc(ptr, layout) }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            count_allocation(new_size);
+            unsafe { System.realloc(ptr, layout, new_size) }
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    // Run `f` and return its result with the number of large allocations it made on this thread
+    fn large_allocations_during<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        let before = LARGE_ALLOCATIONS.with(Cell::get);
+        let result = f();
+        (result, LARGE_ALLOCATIONS.with(Cell::get) - before)
+    }
+
+    #[test]
+    fn large_input_allocates_the_token_vector_once() {
+        let source = "        total = total + 1;   // add one to the running total\n".repeat(50_000);
+        let (tokens, allocations) = large_allocations_during(|| tokenize(&source).unwrap());
+        assert_eq!(tokens.len(), 6 * 50_000);
+        assert_eq!(tokens[6].line, 2);
+        assert_eq!(allocations, 1);
+        // Growing the vector from empty, as the lexer used to, reallocates each time it fills
+        let (_, growing) = large_allocations_during(|| {
+            let mut grown = Vec::new();
+            for token in &tokens {
+                grown.push(token.clone());
+            }
+            grown
+        });
+        assert!(growing > 10, "{}", growing);
+    }
+
+    #[test]
+    fn lexer_yields_tokens_lazily() {
+        // Everything after the third token is invalid, so lexing it would produce errors
+        let source = format!("int main ( {}", "@ ".repeat(100_000));
+        let first: Vec<Token> = Lexer::new(&source, &LexerOptions::default()).take(3).map(|token| token.unwrap().value).collect();
+        assert_eq!(first, vec![IntKeyword, ident("main"), OpenParenthesis]);
+    }
+
+    #[test]
+    fn diagnostic_puts_a_caret_under_the_column() {
+        let rendered = format_diagnostic("int x;\nint @y;\n", 2, 5, "Lexical Error: Invalid character '@'", DEFAULT_TAB_WIDTH);
+        assert_eq!(rendered, "Lexical Error: Invalid character '@'\n  int @y;\n      ^");
+    }
+
+    #[test]
+    fn lexical_error_includes_the_source_snippet() {
+        let error = lex_error("int x;\nint @y;");
+        assert_eq!(error.lines().nth(2), Some("      ^"));
+    }
+
+    #[test]
+    fn tab_advances_to_the_next_tab_stop() {
+        let tokens = tokenize("\treturn;").unwrap();
+        assert_eq!(tokens[0].col, 9);
+        let narrow = LexerOptions { tab_width: 4, ..LexerOptions::default() };
+        let tokens = tokenize_with_options("x\treturn;", &narrow).unwrap();
+        assert_eq!(tokens[1].col, 5);
+    }
+
+    #[test]
+    fn caret_lines_up_after_a_tab() {
+        let error = lex_error("\t@");
+        let lines: Vec<&str> = error.lines().collect();
+        assert!(lines[0].ends_with("at line 1, column 9"), "{}", lines[0]);
+        assert_eq!(lines[1], "          @");
+        assert_eq!(lines[2], "          ^");
+    }
+}
@@ -0,0 +1,274 @@
+// The compiler as a library: `compile` runs the whole pipeline on a source string, so it can be
+// embedded in another program. The command-line driver in main.rs is a thin wrapper over it.
+pub mod arm64; // AArch64 assembly emission from TACKY
+pub mod asm; // x86-64 instruction selection and assembly emission
+pub mod ast; // Abstract syntax tree produced by the parser
+pub mod dot; // Graphviz rendering of the AST
+pub mod interpret; // Tree-walking interpreter for running programs without assembling them
+pub mod lexer; // Lexical analysis of C source
+pub mod lint; // Non-fatal warnings about likely mistakes
+pub mod optimize; // Optional optimization passes
+pub mod parser; // Recursive-descent parser
+pub mod preprocess; // Directive handling and macro expansion before parsing
+pub mod semantics; // Semantic checks on the parsed program
+pub mod tacky; // Three-address intermediate representation
+pub mod typecheck; // Type checking of expressions
+pub mod wasm; // WebAssembly text format emission
+
+use asm::{emit, gen}; // Import the assembly backend passes
+use ast::Program; // Import the AST root
+use lexer::{join_errors, tokenize_with_options, LexerOptions, Spanned, Token, DEFAULT_TAB_WIDTH}; // Import the lexer's public interface
+use lint::{lint, lint_tokens}; // Import the warning checks
+use optimize::{fold_constants, peephole}; // Import the optimization passes
+use parser::parse; // Import the parser entry point
+use preprocess::Preprocessor; // Import the preprocessor
+use semantics::resolve; // Import the semantic analysis pass
+use std::error::Error; // Import the standard error trait, so errors work with '?' and 'Box<dyn Error>'
+use std::fmt; // Import formatting traits for printing errors
+use std::fs; // Import file reading, for compiling a file by path
+use std::io; // Import the I/O error type
+use std::path::{Path, PathBuf}; // Import path types for where the source came from
+use tacky::{emit_tacky, TackyProgram}; // Import the TACKY lowering pass
+use typecheck::typecheck; // Import the type checking pass
+
+/// The last phase `compile` runs, which decides what it returns.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Phase {
+    Lex,     // Stop with the tokens, after preprocessing
+    Parse,   // Stop with the AST as written
+    Analyze, // Stop with the AST after name resolution and type checking
+    Tacky,   // Stop with the TACKY intermediate representation
+    #[default]
+    Codegen, // Run every phase and produce assembly
+}
+
+/// The instruction set `compile` generates assembly for.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    X86_64, // Linux x86-64, in AT&T syntax
+    Arm64,  // Apple Silicon AArch64, with Mach-O symbol names
+    Wasm,   // WebAssembly, as a text format (WAT) module
+}
+
+impl Target {
+    // Every target, with the name '--target' knows it by
+    pub const ALL: [(&'static str, Target); 3] = [("x86_64", Target::X86_64), ("arm64", Target::Arm64), ("wasm", Target::Wasm)];
+
+    // Look up a target by its name
+    pub fn from_name(name: &str) -> Option<Target> {
+        Target::ALL.iter().find(|(known, _)| *known == name).map(|(_, target)| *target)
+    }
+
+    // The target for the machine the compiler was built for, or x86-64 when no backend
+    // matches it; the arm64 backend only writes Mach-O names, so only macOS counts for it
+    pub fn host() -> Target {
+        if cfg!(all(target_arch = "aarch64", target_os = "macos")) {
+            Target::Arm64
+        } else {
+            Target::X86_64
+        }
+    }
+}
+
+impl Default for Target {
+    fn default() -> Self {
+        Target::host()
+    }
+}
+
+/// Settings for one call to `compile`.
+#[derive(Clone)]
+pub struct Options {
+    pub stop_after: Phase,
+    pub target: Target,
+    pub path: PathBuf,             // Where the source was read from; '#include "..."' looks next to it
+    pub predefined: Preprocessor,  // Macros defined before the source is read, as with '-D'
+    pub trigraphs: bool,           // Replace trigraphs like '??<' before preprocessing, as with '-trigraphs'
+    pub optimize: bool,            // Fold constant expressions and remove redundant instructions, as with '-O1'
+    pub verbose: bool,             // Print each token to stderr as it is found
+    pub tab_width: usize,          // Columns between tab stops when counting error positions, as with '-ftabstop'
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            stop_after: Phase::default(),
+            target: Target::default(),
+            path: PathBuf::new(),
+            predefined: Preprocessor::default(),
+            trigraphs: false,
+            optimize: false,
+            verbose: false,
+            tab_width: DEFAULT_TAB_WIDTH,
+        }
+    }
+}
+
+/// What `compile` produced, according to the phase it stopped after.
+#[derive(Debug, Clone)]
+pub enum Artifact {
+    Tokens(Vec<Spanned<Token>>), // From `Phase::Lex`
+    Ast(Program),                // From `Phase::Parse` and `Phase::Analyze`
+    Tacky(TackyProgram),         // From `Phase::Tacky`
+    Assembly(String),            // From `Phase::Codegen`
+}
+
+/// The result of a successful compilation.
+#[derive(Debug, Clone)]
+pub struct CompileOutput {
+    pub artifact: Artifact,
+    pub warnings: Vec<String>, // Warnings in the order they were found; they never stop compilation
+}
+
+/// Why compilation failed, by the phase that rejected the source. Each message is already
+/// worded for users; the positions are there for callers that want to point at the source.
+#[derive(Debug)]
+pub enum CompileError {
+    Io(io::Error),                                  // The source file couldn't be read
+    Preprocess(String),                             // A directive is malformed or unsupported
+    Lex { msg: String, line: usize, col: usize },   // Invalid tokens, one message per line, at the first one's position
+    Parse { msg: String, line: usize, col: usize }, // The tokens don't form a valid program
+    Semantic(String),                               // A semantic, type, or code generation error
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileError::Io(e) => write!(f, "Error: {}", e),
+            CompileError::Preprocess(msg)
+            | CompileError::Lex { msg, .. }
+            | CompileError::Parse { msg, .. }
+            | CompileError::Semantic(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl Error for CompileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CompileError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+// Read a C file and compile it like `compile`, looking for its '#include' files next to it
+pub fn compile_file(path: &Path, options: Options) -> Result<CompileOutput, CompileError> {
+    let source = fs::read_to_string(path).map_err(CompileError::Io)?;
+    compile(&source, Options { path: path.to_path_buf(), ..options })
+}
+
+// Compile C source up to the phase `options` asks for. Warnings raised before a failure are
+// dropped along with the rest of the output.
+pub fn compile(source: &str, options: Options) -> Result<CompileOutput, CompileError> {
+    let mut preprocessor = options.predefined;
+    let predefined_warnings = preprocessor.warnings().len(); // Those belong to whoever defined the macros
+    preprocessor.set_trigraphs(options.trigraphs);
+    let source = preprocessor.process(source, &options.path).map_err(CompileError::Preprocess)?;
+    let mut warnings = preprocessor.warnings()[predefined_warnings..].to_vec();
+    // Report every lexical error, one per line; the tab width places each error's caret too
+    let lexer_options = LexerOptions { tab_width: options.tab_width, ..LexerOptions::default() };
+    let tokens = tokenize_with_options(&source, &lexer_options).map_err(|errors| {
+        let msg = join_errors(&errors);
+        CompileError::Lex { msg, line: errors[0].line, col: errors[0].col }
+    })?;
+    let tokens = preprocessor.expand(tokens);
+    if options.verbose {
+        for token in &tokens {
+            eprintln!("DEBUG: Found {:?} at line {}, column {}", token.value, token.line, token.col);
+        }
+    }
+    if options.stop_after == Phase::Lex {
+        return Ok(CompileOutput { artifact: Artifact::Tokens(tokens), warnings });
+    }
+
+    let token_warnings = lint_tokens(&tokens); // Some checks need the tokens the parser consumes
+    let program = parse(tokens).map_err(|e| CompileError::Parse { msg: e.value, line: e.line, col: e.col })?;
+    if options.stop_after == Phase::Parse {
+        return Ok(CompileOutput { artifact: Artifact::Ast(program), warnings });
+    }
+
+    // Reject programs that parse but are not valid C, and give each variable a unique name
+    let program = resolve(&program).map_err(CompileError::Semantic)?;
+    typecheck(&program).map_err(CompileError::Semantic)?; // Reject expressions that use a 'void' value
+    warnings.extend(token_warnings);
+    warnings.extend(lint(&program));
+    let program = if options.optimize { fold_constants(&program) } else { program };
+    if options.stop_after == Phase::Analyze {
+        return Ok(CompileOutput { artifact: Artifact::Ast(program), warnings });
+    }
+
+    // The wasm backend keeps the AST's structured control flow, which TACKY has flattened
+    if options.target == Target::Wasm && options.stop_after == Phase::Codegen {
+        let wat = wasm::gen(&program).map_err(CompileError::Semantic)?;
+        return Ok(CompileOutput { artifact: Artifact::Assembly(wat), warnings });
+    }
+
+    // The arm64 backend starts from TACKY, while the x86-64 one lowers the AST itself
+    if options.stop_after == Phase::Tacky || options.target == Target::Arm64 {
+        let tacky = emit_tacky(&program).map_err(CompileError::Semantic)?;
+        let artifact = match options.stop_after {
+            Phase::Tacky => Artifact::Tacky(tacky),
+            _ => Artifact::Assembly(arm64::gen(&tacky).map_err(CompileError::Semantic)?),
+        };
+        return Ok(CompileOutput { artifact, warnings });
+    }
+
+    let instructions = gen(&program).map_err(CompileError::Semantic)?;
+    let instructions = if options.optimize { peephole(&instructions) } else { instructions };
+    Ok(CompileOutput { artifact: Artifact::Assembly(emit(&instructions)), warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Options that stop after the given phase, with every other setting at its default
+    fn stop_after(phase: Phase) -> Options {
+        Options { stop_after: phase, ..Options::default() }
+    }
+
+    #[test]
+    fn compile_returns_the_tokens_when_stopping_after_lexing() {
+        let output = compile("int main(void) { return 0; }", stop_after(Phase::Lex)).unwrap();
+        let Artifact::Tokens(tokens) = output.artifact else { panic!("expected tokens") };
+        assert_eq!(tokens.len(), 10);
+        assert_eq!(tokens[0].value, Token::IntKeyword);
+        assert_eq!(tokens[1].value, Token::Identifier("main".to_string()));
+        assert!(output.warnings.is_empty());
+    }
+
+    #[test]
+    fn compile_produces_assembly_by_default() {
+        let options = Options { target: Target::X86_64, ..Options::default() };
+        let Artifact::Assembly(assembly) = compile("int main(void) { return 2; }", options).unwrap().artifact else {
+            panic!("expected assembly")
+        };
+        assert!(assembly.contains("movl $2, %eax"));
+    }
+
+    #[test]
+    fn compile_returns_the_warnings() {
+        let output = compile("int f(int x) { if (x) return 1; } int main(void) { return f(1); }", stop_after(Phase::Analyze)).unwrap();
+        assert_eq!(output.warnings, vec!["warning: control reaches end of non-void function 'f'"]);
+    }
+
+    #[test]
+    fn lexical_failure_is_the_lex_variant_with_its_position() {
+        match compile("int main(void) {\n  return @;\n}", stop_after(Phase::Lex)) {
+            Err(CompileError::Lex { msg, line, col }) => {
+                assert_eq!((line, col), (2, 10));
+                assert!(msg.starts_with("Lexical Error: Invalid character '@' at line 2, column 10"));
+            }
+            other => panic!("expected a lexical error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn each_phase_fails_with_its_own_variant() {
+        assert!(matches!(compile("int main(void) { return }", Options::default()), Err(CompileError::Parse { line: 1, col: 25, .. })));
+        assert!(matches!(compile("int main(void) { return x; }", Options::default()), Err(CompileError::Semantic(_))));
+        assert!(matches!(compile("#bogus\n", Options::default()), Err(CompileError::Preprocess(_))));
+        assert!(matches!(compile_file(Path::new("no/such/file.c"), Options::default()), Err(CompileError::Io(_))));
+    }
+}
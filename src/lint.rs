@@ -0,0 +1,149 @@
+// Warnings: analyses that point out likely mistakes in a valid program without rejecting it
+use crate::ast::{BlockItem, Expr, Function, Program, Statement, Type}; // Import the AST node types
+use crate::lexer::{Spanned, Token}; // Import the token types for checks that need source positions
+
+// Collect every warning for the program, in source order
+pub fn lint(program: &Program) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for function in &program.functions {
+        // Falling off the end of 'main' returns 0, so only other functions are checked
+        if function.return_type == Type::Int && function.name != "main" && !function_returns(function) {
+            warnings.push(format!("warning: control reaches end of non-void function '{}'", function.name));
+        }
+    }
+    warnings
+}
+
+// Collect the warnings that need the spelling of the source, in source order. The AST
+// forgets redundant parentheses and positions, so these look at the tokens instead.
+pub fn lint_tokens(tokens: &[Spanned<Token>]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let opens_condition = matches!(token.value, Token::IfKeyword | Token::WhileKeyword)
+            && matches!(tokens.get(i + 1), Some(Spanned { value: Token::OpenParenthesis, .. }));
+        if !opens_condition {
+            continue;
+        }
+        // 'if (x = 5)' is usually a mistyped '=='; 'if ((x = 5))' says the assignment is meant
+        if let Some(assign) = top_level_assignment(&tokens[i + 2..]) {
+            warnings.push(format!(
+                "warning: assignment used as a condition at line {}, column {}; use '==' to compare, or wrap the assignment in parentheses if it is intended",
+                assign.line, assign.col
+            ));
+        }
+    }
+    warnings
+}
+
+// Find an '=' at the outermost level of a condition, given the tokens after its '('.
+// Any other operator binding more loosely than '=' there means the condition as a whole
+// isn't an assignment.
+fn top_level_assignment(tokens: &[Spanned<Token>]) -> Option<&Spanned<Token>> {
+    let mut depth = 0;
+    for token in tokens {
+        match token.value {
+            Token::OpenParenthesis | Token::OpenBracket => depth += 1,
+            Token::CloseParenthesis | Token::CloseBracket if depth == 0 => return None, // The end of the condition
+            Token::CloseParenthesis | Token::CloseBracket => depth -= 1,
+            Token::Question | Token::Comma if depth == 0 => return None,
+            Token::Assign if depth == 0 => return Some(token),
+            _ => {}
+        }
+    }
+    None
+}
+
+// Whether every path through a function body ends in a 'return'
+fn function_returns(function: &Function) -> bool {
+    items_return(&function.body)
+}
+
+// A sequence returns once any statement in it does; what follows is unreachable
+fn items_return(items: &[BlockItem]) -> bool {
+    items.iter().any(|item| matches!(item, BlockItem::Statement(statement) if always_returns(statement)))
+}
+
+// Whether control can never run past the end of a statement without returning
+fn always_returns(statement: &Statement) -> bool {
+    match statement {
+        Statement::Return(_) => true,
+        Statement::If { then, else_branch: Some(else_branch), .. } => always_returns(then) && always_returns(else_branch),
+        Statement::Block(items) => items_return(items),
+        Statement::Label(_, statement) => always_returns(statement),
+        // A loop that never tests false only ends through a 'break'
+        Statement::While { cond, body } => is_nonzero_constant(cond) && !breaks(body),
+        // The body runs at least once, so returning from it is enough
+        Statement::DoWhile { body, cond } => always_returns(body) || (is_nonzero_constant(cond) && !breaks(body)),
+        Statement::For { cond, body, .. } => cond.as_ref().is_none_or(is_nonzero_constant) && !breaks(body),
+        // A switch may skip all its cases, so it is treated as possibly falling through
+        _ => false,
+    }
+}
+
+// Whether an expression is a constant that is always true
+fn is_nonzero_constant(expr: &Expr) -> bool {
+    matches!(expr, Expr::Constant(value) if *value != 0)
+}
+
+// Whether a loop body contains a 'break' that leaves that loop; breaks inside an inner
+// loop or switch leave only that one
+fn breaks(statement: &Statement) -> bool {
+    match statement {
+        Statement::Break => true,
+        Statement::If { then, else_branch, .. } => breaks(then) || else_branch.as_deref().is_some_and(breaks),
+        Statement::Block(items) => {
+            items.iter().any(|item| matches!(item, BlockItem::Statement(statement) if breaks(statement)))
+        }
+        Statement::Label(_, statement) | Statement::Case(_, statement) | Statement::Default(statement) => breaks(statement),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    // Lex and parse a source, then collect the program's warnings
+    fn lint_source(source: &str) -> Vec<String> {
+        lint(&parse(tokenize(source).unwrap()).unwrap())
+    }
+
+    // Lex a 'main' with the given body and collect the warnings found in its tokens
+    fn lint_body_tokens(body: &str) -> Vec<String> {
+        lint_tokens(&tokenize(&format!("int main(void) {{ {} }}", body)).unwrap())
+    }
+
+    #[test]
+    fn function_that_always_returns_has_no_warning() {
+        assert!(lint_source("int f(int x) { if (x) return 1; else return 2; } int main(void) { return f(1); }").is_empty());
+        assert!(lint_source("int f(void) { { return 1; } } int main(void) { return f(); }").is_empty());
+    }
+
+    #[test]
+    fn conditional_return_warns() {
+        assert_eq!(
+            lint_source("int f(int x) { if (x) return 1; } int main(void) { return f(1); }"),
+            vec!["warning: control reaches end of non-void function 'f'"]
+        );
+    }
+
+    #[test]
+    fn main_and_void_functions_may_fall_off_the_end() {
+        assert!(lint_source("void f(void) { } int main(void) { f(); }").is_empty());
+    }
+
+    #[test]
+    fn assignment_as_condition_warns() {
+        let warnings = lint_body_tokens("int x = 0; if (x = 5) return 1; while (x = 0) { } return 0;");
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("warning: assignment used as a condition at line 1, column 35;"), "{}", warnings[0]);
+    }
+
+    #[test]
+    fn double_parentheses_suppress_the_assignment_warning() {
+        assert!(lint_body_tokens("int x = 0; if ((x = 5)) return 1; return 0;").is_empty());
+        assert!(lint_body_tokens("int x = 0; if (x == 5) return 1; return 0;").is_empty());
+    }
+}
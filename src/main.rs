@@ -1,237 +1,352 @@
+use compiler::ast::unparse; // Import the AST-to-source printer
+use compiler::dot::to_dot; // Import the AST-to-DOT printer
+use compiler::interpret; // Import the interpreter behind '--run'
+use compiler::lexer::tokens_to_json; // Import the JSON token printer
+use compiler::semantics::dump_symbols; // Import the symbol table dump
+use compiler::{compile, compile_file, Artifact, CompileError, Options, Phase, Target}; // Import the library entry points
 use std::env; // Import standard library module for handling command-line arguments
-use std::fs::File; // Import module to handle file operations
-use std::io::{self, Read}; // Import modules for input/output operations
+use std::fs; // Import module to handle file operations
+use std::io::{self, Read}; // Import modules for input operations
+use std::path::{Path, PathBuf}; // Import path types, so included files are found relative to the input
 use std::process; // Import module to handle process termination
 
-// Define an enumeration for different types of tokens recognized by the lexer
-#[derive(Debug)]
-enum Token {
-    Identifier(String), // Represents variable/function names
-    Constant(String), // Represents numeric constants
-    IntKeyword, // 'int' keyword
-    VoidKeyword, // 'void' keyword
-    ReturnKeyword, // 'return' keyword
-    OpenParenthesis, // '('
-    CloseParenthesis, // ')'
-    OpenBrace, // '{'
-    CloseBrace, // '}'
-    Semicolon, // ';'
+// Path argument that makes the compiler read the program from standard input
+const STDIN_PATH: &str = "-";
+
+// Process exit codes, one per kind of failure, so scripts can tell them apart
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExitCode {
+    Success = 0,         // Every input was processed
+    CompileError = 1,    // A semantic, type, or code generation error
+    UsageError = 2,      // Bad command-line arguments
+    FileError = 3,       // An input couldn't be read or an output couldn't be written
+    LexError = 4,        // The source contains an invalid token
+    ParseError = 5,      // The tokens don't form a valid program
+    PreprocessError = 6, // A directive is malformed or unsupported
+    RuntimeError = 7,    // A program run with '--run' did something undefined, like dividing by zero
 }
 
-// Function to perform lexical analysis on a given file
-fn lexer(file_path: &str) -> Result<(), String> {
-    // Try to open the specified file
-    let mut file = match File::open(file_path) {
-        Ok(file) => file, // File opened successfully
-        Err(_) => {
-            return Err(format!("Error: Could not open file '{}'.", file_path)); // Return an error message
-        }
-    };
+// An error message together with the exit code it should end the process with
+type Failure = (ExitCode, String);
+
+// Print an error and end the process with the given code
+fn exit_with(code: ExitCode, message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(code as i32);
+}
 
-    let mut contents = String::new(); // Create a mutable string to store file contents
-    // Read file contents into the string
-    if let Err(_) = file.read_to_string(&mut contents) {
-        return Err(format!("Error: Could not read file '{}'.", file_path)); // Return an error message
+// Read the whole program piped to standard input into a string
+fn read_stdin() -> Result<String, String> {
+    let mut contents = String::new(); // Create a mutable string to store the piped program
+    if io::stdin().read_to_string(&mut contents).is_err() {
+        return Err("Error: Could not read from standard input.".to_string()); // Return an error message
     }
+    Ok(contents)
+}
 
-    let mut tokens = Vec::new(); // Create a vector to store identified tokens
-    let mut chars = contents.chars().peekable(); // Convert file content into an iterator over characters
+// Read a file (or standard input for "-") and compile it up to the given phase with the
+// command-line settings, printing its warnings to stderr
+fn compile_input(path: &str, stop_after: Phase, settings: &Options) -> Result<Artifact, Failure> {
+    let options = Options { stop_after, path: PathBuf::from(path), ..settings.clone() };
+    let result = if path == STDIN_PATH {
+        let source = read_stdin().map_err(|e| (ExitCode::FileError, e))?;
+        compile(&source, options)
+    } else {
+        compile_file(Path::new(path), options)
+    };
+    let output = result.map_err(|e| match e {
+        // Name the file, which the I/O error itself doesn't
+        CompileError::Io(e) if matches!(e.kind(), io::ErrorKind::NotFound | io::ErrorKind::PermissionDenied) => {
+            (ExitCode::FileError, format!("Error: Could not open file '{}'.", path))
+        }
+        CompileError::Io(_) => (ExitCode::FileError, format!("Error: Could not read file '{}'.", path)), // Opened, but not readable text
+        CompileError::Preprocess(_) => (ExitCode::PreprocessError, e.to_string()),
+        CompileError::Lex { .. } => (ExitCode::LexError, e.to_string()),
+        CompileError::Parse { .. } => (ExitCode::ParseError, e.to_string()),
+        CompileError::Semantic(_) => (ExitCode::CompileError, e.to_string()),
+    })?;
+    for warning in output.warnings {
+        eprintln!("{}", warning); // Warnings never stop compilation
+    }
+    Ok(output.artifact)
+}
 
-    // Iterate through each character in the file
-    while let Some(&c) = chars.peek() {
-        match c {
-            ' ' | '\n' | '\t' => {
-                chars.next(); // Ignore whitespace characters
-            }
-            '(' => {
-                tokens.push(Token::OpenParenthesis); // Recognize opening parenthesis
-                chars.next(); // Consume the character
-            }
-            ')' => {
-                tokens.push(Token::CloseParenthesis); // Recognize closing parenthesis
-                chars.next(); // Consume the character
-            }
-            '{' => {
-                tokens.push(Token::OpenBrace); // Recognize opening brace
-                chars.next(); // Consume the character
-            }
-            '}' => {
-                tokens.push(Token::CloseBrace); // Recognize closing brace
-                chars.next(); // Consume the character
-            }
-            ';' => {
-                tokens.push(Token::Semicolon); // Recognize semicolon
-                chars.next(); // Consume the character
-            }
-            ///////////////////////
-            '0'..='9' => {
-                let mut num = String::new(); // Create a string to hold numeric constant
-                while let Some(&d) = chars.peek() {
-                    if d.is_numeric() {
-                        num.push(d); // Add digit to the number string
-                        chars.next(); // Consume the character
-                    } else {
-                        break; // Break if the character is no longer numeric
-                    }
-                }
-                // Ensure the number is not followed by an identifier
-                if let Some(&next) = chars.peek() {
-                    if next.is_alphabetic() || next == '_' {
-                        return Err(format!("Lexical Error: Identifiers cannot start with a number: '{}{}'", num, next));
-                    }
-                }
-                tokens.push(Token::Constant(num)); // Store numeric constants
-            }
-            //////////////////////////////
-            'a'..='z' | 'A'..='Z' | '_' => {
-                let mut ident = String::new(); // Create a string to hold identifier
-                while let Some(&d) = chars.peek() {
-                    if d.is_alphanumeric() || d == '_' {
-                        ident.push(d); // Add character to the identifier string
-                        chars.next(); // Consume the character
-                    } else {
-                        break; // Break if the character is no longer part of an identifier
-                    }
-                }
+// Options that select which stage of the pipeline to run
+const OPTIONS: [&str; 10] = [
+    "--lex", "--tokens-json", "--parse", "--ast-dot", "--format", "--dump-symbols", "--tacky", "--codegen", "-s", "--run",
+];
 
-                eprintln!("DEBUG: Found identifier '{}'", ident);
+// Phases accepted by '--emit', each with the option that stops after it and prints its result
+const EMIT_PHASES: [(&str, &str); 4] = [("tokens", "--lex"), ("ast", "--parse"), ("tacky", "--tacky"), ("asm", "--codegen")];
 
-                // Check if the identifier starts with a number
-                if ident.chars().next().unwrap().is_numeric() {
-                    return Err(format!("Lexical Error: Identifiers cannot start with a number: '{}'", ident));
-                }
+// Run the selected option on a single input file
+fn run(option: Option<&str>, path: &str, output: Option<&str>, settings: &Options) -> Result<(), Failure> {
+    // Ensure the file has a .c extension (standard input has no name to check)
+    if path != STDIN_PATH && !path.ends_with(".c") {
+        return Err((ExitCode::UsageError, "Error: The file must have a .c extension.".to_string()));
+    }
 
-                // Check if the identifier is just an underscore
-                if ident == "_" {
-                    return Err(format!("Lexical Error: Standalone underscore '_' is not a valid identifier."));
-                }
+    let stop_after = match option {
+        // The '--lex' banner waits for the result, since an empty file prints nothing at all
+        Some("--lex") | Some("--tokens-json") => Phase::Lex,
+        Some("--parse") => {
+            println!("Performing parsing on {}", path);
+            Phase::Parse
+        }
+        Some("--ast-dot") | Some("--format") => Phase::Parse,
+        Some("--dump-symbols") | Some("--run") => Phase::Analyze,
+        Some("--tacky") => Phase::Tacky,
+        Some("--codegen") => {
+            println!("Performing code generation on {}", path);
+            Phase::Codegen
+        }
+        Some("-s") => Phase::Codegen, // Refuse to emit assembly for an invalid program
+        _ => return Ok(()), // No option provided, proceed to default behavior
+    };
 
-                // Check if the identifier contains invalid characters
-                if ident.chars().any(|ch| !(ch.is_alphanumeric() || ch == '_')) {
-                    return Err(format!("Lexical Error: Invalid identifier '{}'", ident));
-                }
+    // Every file gets the '--lex' banner except an empty one that lexed, so each error follows
+    // the banner of the file it came from
+    let result = compile_input(path, stop_after, settings);
+    if option == Some("--lex") && !matches!(&result, Ok(Artifact::Tokens(tokens)) if tokens.is_empty()) {
+        println!("Performing lexical analysis on {}", path);
+    }
 
-                // Match known keywords or treat as a generic identifier
-                match ident.as_str() {
-                    "int" => tokens.push(Token::IntKeyword), // Recognize 'int' keyword
-                    "void" => tokens.push(Token::VoidKeyword), // Recognize 'void' keyword
-                    "return" => tokens.push(Token::ReturnKeyword), // Recognize 'return' keyword
-                    _ => tokens.push(Token::Identifier(ident)), // Otherwise, it's a generic identifier
-                }
+    // Each option asks for the phase that produces the artifact it prints
+    match (option, result?) {
+        (Some("--lex"), Artifact::Tokens(tokens)) => {
+            // Print the identified tokens
+            for token in tokens {
+                println!("{}:{} {:?}", token.line, token.col, token.value);
             }
-            '/' => {
-                chars.next(); // Consume the '/' character
-                if let Some(&next) = chars.peek() {
-                    if next == '/' {
-                        while let Some(&c) = chars.peek() {
-                            if c == '\n' { // End of single-line comment
-                                break;
-                            }
-                            chars.next(); // Consume the character
-                        }
-                    } else if next == '*' {
-                        chars.next(); // Consume '*'
-                        while let Some(_) = chars.peek() {
-                            if chars.next() == Some('*') && chars.peek() == Some(&'/') {
-                                chars.next(); // Consume '/'
-                                break; // End of multi-line comment
-                            }
-                        }
-                    } else {
-                        return Err("Lexical Error: Unhandled token '/'. Implement operator support.".to_string());
-                    }
+        }
+        (Some("--tokens-json"), Artifact::Tokens(tokens)) => {
+            println!("{}", tokens_to_json(&tokens)); // Machine-readable tokens for editor tooling
+        }
+        (Some("--parse"), Artifact::Ast(program)) => println!("{:#?}", program), // Pretty-print the tree for inspection
+        (Some("--ast-dot"), Artifact::Ast(program)) => print!("{}", to_dot(&program)), // Graphviz source; pipe it into 'dot -Tpng'
+        (Some("--format"), Artifact::Ast(program)) => print!("{}", unparse(&program)), // Reprint the program as normalized C source
+        (Some("--dump-symbols"), Artifact::Ast(program)) => {
+            print!("{}", dump_symbols(&program)); // Every declared name with its unique renamed identifier
+        }
+        (Some("--run"), Artifact::Ast(program)) => {
+            // The program's result becomes the compiler's exit code, as if it had been built and run
+            let value = interpret::run(&program).map_err(|e| (ExitCode::RuntimeError, e))?;
+            process::exit(value);
+        }
+        (Some("--tacky"), Artifact::Tacky(tacky)) => print!("{}", tacky), // Print the three-address IR
+        (Some("--codegen"), Artifact::Assembly(assembly)) => print!("{}", assembly), // Print the assembly instead of writing a file
+        (_, Artifact::Assembly(assembly)) => {
+            let asm_file = match output {
+                Some(output) => output.to_string(), // Write exactly where '-o' says
+                None if path == STDIN_PATH => {
+                    print!("{}", assembly); // Assembly for piped input goes to standard output instead of a file
+                    return Ok(());
                 }
+                None => format!("{}.s", path.trim_end_matches(".c")), // Swap the .c extension for .s
+            };
+            if fs::write(&asm_file, assembly).is_err() {
+                return Err((ExitCode::FileError, format!("Error: Failed to create assembly file '{}'", asm_file))); // Handle failure
             }
-            _ => {
-                return Err(format!("Lexical Error: Invalid character '{}'", c)); // Handle invalid characters
-            }
+            println!("Generated assembly file: {}", asm_file); // Successfully created assembly file
         }
+        _ => unreachable!("each option's phase produces the artifact it prints"),
     }
+    Ok(())
+}
 
-    // Print the identified tokens
-    for token in tokens {
-        println!("{:?}", token);
+// Record the option that selects the pipeline stage; only one may be given, wherever it appears
+fn set_option<'a>(option: &mut Option<(&'a str, String)>, name: &'a str, spelled: String) {
+    if let Some((_, first)) = option {
+        let message = format!("Error: Conflicting options '{}' and '{}'; give only one.", first, spelled);
+        exit_with(ExitCode::UsageError, &message);
     }
+    *option = Some((name, spelled));
+}
 
-    Ok(())
+// Replace each '@file' argument with the arguments listed in that file, one per line.
+// Blank lines and lines starting with '#' are skipped.
+fn expand_response_files(args: Vec<String>) -> Result<Vec<String>, String> {
+    let mut expanded = Vec::new();
+    for arg in args {
+        let Some(file_path) = arg.strip_prefix('@').filter(|path| !path.is_empty()) else {
+            expanded.push(arg); // An ordinary argument
+            continue;
+        };
+        let contents = fs::read_to_string(file_path)
+            .map_err(|_| format!("Error: Could not read response file '{}'.", file_path))?;
+        for line in contents.lines() {
+            let line = line.trim();
+            if !line.is_empty() && !line.starts_with('#') {
+                expanded.push(line.to_string());
+            }
+        }
+    }
+    Ok(expanded)
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect(); // Collect command-line arguments
+    // Collect command-line arguments, splicing in any response files
+    let args = match expand_response_files(env::args().collect()) {
+        Ok(args) => args,
+        Err(e) => exit_with(ExitCode::FileError, &e),
+    };
 
     // Ensure the user provides at least one argument (the path to the C file)
     if args.len() < 2 {
-        eprintln!("Usage: {} [option] <path-to-C-file>", args[0]);
+        eprintln!("Usage: {} [option] [--verbose] [-o <path>] <path-to-C-file | ->...", args[0]);
         eprintln!("Options:");
         eprintln!("  --lex       Perform lexical analysis");
+        eprintln!("  --tokens-json  Print the tokens as a JSON array");
         eprintln!("  --parse     Perform parsing");
-        eprintln!("  --codegen   Perform code generation");
+        eprintln!("  --ast-dot   Print the AST as a Graphviz DOT graph");
+        eprintln!("  --format    Print the program back as formatted C source");
+        eprintln!("  --dump-symbols  Print every declared function and variable after resolution");
+        eprintln!("  --tacky     Print the TACKY intermediate representation");
+        eprintln!("  --codegen   Perform code generation and print the assembly");
         eprintln!("  -s          Generate an assembly file");
-        process::exit(1); // Exit if the arguments are invalid
+        eprintln!("  --run       Interpret the program and exit with the value 'main' returns");
+        eprintln!("  --emit <phase>  Stop after tokens, ast, tacky, or asm and print the result");
+        eprintln!("  --target <name>  Generate assembly for x86_64, arm64, or wasm; defaults to the host");
+        eprintln!("  -o <path>   Write the assembly file to <path>");
+        eprintln!("  -D <name>[=<value>]  Define a macro before reading the inputs; the value defaults to 1");
+        eprintln!("  -trigraphs  Replace the nine '??' trigraphs, like '??<' for '{{', before preprocessing");
+        eprintln!("  -ftabstop=<n>  Count a tab as reaching the next multiple of <n> columns; defaults to 8");
+        eprintln!("  -O1         Fold constant expressions and remove redundant instructions");
+        eprintln!("  --keep-going  With several inputs, keep compiling the rest after one fails");
+        eprintln!("  --verbose   Print each token the lexer finds to stderr");
+        eprintln!("  -V, --version  Print the compiler version");
+        eprintln!("Pass - as the path to read the program from standard input.");
+        eprintln!("Pass @file to read more arguments from a file, one per line.");
+        eprintln!("Exit codes: 0 success, 1 compile error, 2 usage error, 3 file error, 4 lexical error, 5 parse error, 6 preprocessor error, 7 runtime error under --run.");
+        process::exit(ExitCode::UsageError as i32); // Exit if the arguments are invalid
+    }
+
+    // Print the version and stop, whatever else is on the command line
+    if args.iter().skip(1).any(|arg| arg == "--version" || arg == "-V") {
+        println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+        process::exit(ExitCode::Success as i32);
+    }
+
+    let mut option: Option<(&str, String)> = None; // The option, with how it was spelled for error messages
+    let mut keep_going = false;
+    let mut output: Option<&String> = None;
+    let mut settings = Options::default(); // Flags that apply to every input, including the '-D' macros
+    let mut paths: Vec<&String> = Vec::new();
+
+    // Flags and the option may appear anywhere; any other argument starting with '-' is the option
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        if arg == "--verbose" {
+            settings.verbose = true; // Trace the lexer's output
+        } else if arg == "--keep-going" {
+            keep_going = true; // Attempt every input even after one fails
+        } else if arg == "-O1" {
+            settings.optimize = true; // Run the optimization passes
+        } else if arg == "-trigraphs" {
+            settings.trigraphs = true; // Replace trigraphs like '??<' before preprocessing
+        } else if let Some(width) = arg.strip_prefix("-ftabstop=") {
+            // Columns between tab stops, for the positions in error messages
+            match width.parse::<usize>() {
+                Ok(width) if width > 0 => settings.tab_width = width,
+                _ => {
+                    let message = format!("Error: Invalid tab width '{}' for '-ftabstop'; expected a positive number", width);
+                    exit_with(ExitCode::UsageError, &message);
+                }
+            }
+        } else if let Some(definition) = arg.strip_prefix("-D") {
+            // '-D NAME' or '-DNAME' defines NAME as 1, and '-D NAME=VALUE' as VALUE
+            let definition = match definition {
+                "" => match iter.next() {
+                    Some(definition) => definition.as_str(),
+                    None => exit_with(ExitCode::UsageError, "Error: Missing macro definition after '-D'."),
+                },
+                definition => definition,
+            };
+            let (name, value) = definition.split_once('=').unwrap_or((definition, "1"));
+            if let Err(e) = settings.predefined.define(name, value) {
+                exit_with(ExitCode::UsageError, &e);
+            }
+        } else if arg == "--target" {
+            // The instruction set the assembly is for
+            let Some(name) = iter.next() else {
+                exit_with(ExitCode::UsageError, "Error: Missing target after '--target'.");
+            };
+            match Target::from_name(name) {
+                Some(target) => settings.target = target,
+                None => {
+                    let names: Vec<&str> = Target::ALL.iter().map(|(name, _)| *name).collect();
+                    let message = format!("Error: Unknown target '{}' for '--target'; expected one of: {}", name, names.join(", "));
+                    exit_with(ExitCode::UsageError, &message);
+                }
+            }
+        } else if arg == "-o" {
+            // The output path is the next argument
+            match iter.next() {
+                Some(path) => output = Some(path),
+                None => exit_with(ExitCode::UsageError, "Error: Missing output path after '-o'."),
+            }
+        } else if arg == "--emit" {
+            // '--emit <phase>' is another way to spell the option that stops after that phase
+            let Some(phase) = iter.next() else {
+                exit_with(ExitCode::UsageError, "Error: Missing phase after '--emit'.");
+            };
+            match EMIT_PHASES.iter().find(|(name, _)| *name == phase) {
+                Some((_, alias)) => set_option(&mut option, alias, format!("--emit {}", phase)),
+                None => {
+                    let names: Vec<&str> = EMIT_PHASES.iter().map(|(name, _)| *name).collect();
+                    let message = format!("Error: Unknown phase '{}' for '--emit'; expected one of: {}", phase, names.join(", "));
+                    exit_with(ExitCode::UsageError, &message);
+                }
+            }
+        } else if arg.starts_with('-') && arg != STDIN_PATH {
+            set_option(&mut option, arg, arg.clone());
+        } else {
+            paths.push(arg); // A path to a C file
+        }
     }
 
-    let mut path_index = 1;
-    let mut option: Option<&String> = None;
+    for warning in settings.predefined.warnings() {
+        eprintln!("{}", warning); // A macro defined twice with '-D'
+    }
 
-    // Check if the first argument is an option
-    if args[1].starts_with('-') {
-        option = Some(&args[1]);
-        path_index = 2; // The path to the C file will be the next argument
+    // Reject unknown options before touching any file
+    let option = option.map(|(name, _)| name);
+    if let Some(opt) = option {
+        if !OPTIONS.contains(&opt) {
+            exit_with(ExitCode::UsageError, &format!("Error: Unknown option '{}'", opt)); // Handle unknown options
+        }
     }
 
     // Ensure the path to the C file is provided
-    if path_index >= args.len() {
-        eprintln!("Error: Missing path to C file.");
-        process::exit(1);
+    if paths.is_empty() {
+        exit_with(ExitCode::UsageError, "Error: Missing path to C file.");
     }
 
-    let path = &args[path_index];
+    // A single output path can only name the result of a single input
+    if output.is_some() && paths.len() > 1 {
+        exit_with(ExitCode::UsageError, "Error: Cannot use '-o' with multiple input files.");
+    }
 
-    // Ensure the file has a .c extension
-    if !path.ends_with(".c") {
-        eprintln!("Error: The file must have a .c extension.");
-        process::exit(1);
+    // Running a program ends the process with its result, so there can only be one
+    if option == Some("--run") && paths.len() > 1 {
+        exit_with(ExitCode::UsageError, "Error: Cannot use '--run' with multiple input files.");
     }
 
-    // Handle the different options passed in the command line
-    match option {
-        Some(opt) => match opt.as_str() {
-            "--lex" => {
-                println!("Performing lexical analysis on {}", path);
-                if let Err(e) = lexer(path) {
-                    eprintln!("{}", e); // Print error if lexical analysis fails
-                    process::exit(1); // Exit with an error code
-                }
-                process::exit(0); // Exit successfully
+    // Process the files in order, stopping at the first failure unless asked to keep going
+    let mut status = ExitCode::Success;
+    for path in paths {
+        if let Err((code, e)) = run(option, path, output.map(String::as_str), &settings) {
+            eprintln!("{}", e); // Print error if any stage fails
+            if status == ExitCode::Success {
+                status = code; // The first failure decides the exit code
             }
-            "--parse" => {
-                println!("Performing parsing on {}", path);
-                process::exit(0); // Exit after parsing (not yet implemented)
+            if !keep_going {
+                break;
             }
-            "--codegen" => {
-                println!("Performing code generation on {}", path);
-                process::exit(0); // Exit after code generation (not yet implemented)
-            }
-            "-s" => {
-                let new_name = path.trim_end_matches(".c"); // Trim the .c extension
-                let asm_file = format!("{}.s", new_name); // Generate the assembly file name
-                match File::create(&asm_file) {
-                    Ok(_) => {
-                        println!("Generated assembly file: {}", asm_file); // Successfully created assembly file
-                        process::exit(0); // Exit successfully
-                    }
-                    Err(_) => {
-                        eprintln!("Error: Failed to create assembly file '{}'", asm_file); // Handle failure
-                        process::exit(1); // Exit with error code
-                    }
-                }
-            }
-            _ => {
-                eprintln!("Error: Unknown option '{}'", opt); // Handle unknown options
-                process::exit(1); // Exit with error code
-            }
-        },
-        None => {} // No option provided, proceed to default behavior
+        }
     }
-}
 
+    if status != ExitCode::Success {
+        process::exit(status as i32); // Exit with an error code if any file failed
+    }
+}
@@ -0,0 +1,284 @@
+// Optimization passes enabled by '-O1': constant folding over the AST and a peephole
+// pass over the generated instructions
+use crate::asm::Instruction; // Import the assembly instruction type
+use crate::ast::{BinaryOp, BlockItem, Declaration, Expr, ForInit, Function, Program, Statement, Type, UnaryOp}; // Import the AST node types
+use crate::typecheck::value_type; // Import the type of a checked expression
+
+// Replace every expression built only from constants with the constant it evaluates to.
+// Anything whose value is undefined at run time, like '1 / 0', is left for the program to hit.
+pub fn fold_constants(program: &Program) -> Program {
+    Program {
+        functions: program.functions.iter().map(fold_function).collect(),
+        variables: program.variables.iter().map(fold_declaration).collect(),
+    }
+}
+
+// Fold the constants in one function body
+fn fold_function(function: &Function) -> Function {
+    Function {
+        storage: function.storage.clone(),
+        return_type: function.return_type.clone(),
+        name: function.name.clone(),
+        params: function.params.clone(),
+        unspecified_params: function.unspecified_params,
+        body: function.body.iter().map(fold_block_item).collect(),
+    }
+}
+
+// Fold the constants in a declaration or statement
+fn fold_block_item(item: &BlockItem) -> BlockItem {
+    match item {
+        BlockItem::Declaration(declaration) => BlockItem::Declaration(fold_declaration(declaration)),
+        BlockItem::Statement(statement) => BlockItem::Statement(fold_statement(statement)),
+    }
+}
+
+// Fold the constants in a declaration's initializer
+fn fold_declaration(declaration: &Declaration) -> Declaration {
+    Declaration {
+        storage: declaration.storage.clone(),
+        name: declaration.name.clone(),
+        init: declaration.init.as_ref().map(fold_expr),
+    }
+}
+
+// Fold the constants in every expression inside a statement
+fn fold_statement(statement: &Statement) -> Statement {
+    match statement {
+        Statement::Return(expr) => Statement::Return(expr.as_ref().map(fold_expr)),
+        Statement::Expression(expr) => Statement::Expression(fold_expr(expr)),
+        Statement::If { cond, then, else_branch } => Statement::If {
+            cond: fold_expr(cond),
+            then: Box::new(fold_statement(then)),
+            else_branch: else_branch.as_ref().map(|else_branch| Box::new(fold_statement(else_branch))),
+        },
+        Statement::While { cond, body } => Statement::While { cond: fold_expr(cond), body: Box::new(fold_statement(body)) },
+        Statement::DoWhile { body, cond } => Statement::DoWhile { body: Box::new(fold_statement(body)), cond: fold_expr(cond) },
+        Statement::For { init, cond, post, body } => Statement::For {
+            init: init.as_ref().map(|init| match init {
+                ForInit::Declaration(declaration) => ForInit::Declaration(fold_declaration(declaration)),
+                ForInit::Expression(expr) => ForInit::Expression(fold_expr(expr)),
+            }),
+            cond: cond.as_ref().map(fold_expr),
+            post: post.as_ref().map(fold_expr),
+            body: Box::new(fold_statement(body)),
+        },
+        Statement::Goto(label) => Statement::Goto(label.clone()),
+        Statement::Label(label, statement) => Statement::Label(label.clone(), Box::new(fold_statement(statement))),
+        Statement::Switch { value, body } => Statement::Switch { value: fold_expr(value), body: Box::new(fold_statement(body)) },
+        Statement::Case(value, statement) => Statement::Case(fold_expr(value), Box::new(fold_statement(statement))),
+        Statement::Default(statement) => Statement::Default(Box::new(fold_statement(statement))),
+        Statement::Break => Statement::Break,
+        Statement::Continue => Statement::Continue,
+        Statement::Block(items) => Statement::Block(items.iter().map(fold_block_item).collect()),
+    }
+}
+
+// Fold an expression bottom-up, so folded operands can make their parent foldable too
+fn fold_expr(expr: &Expr) -> Expr {
+    match expr {
+        // An operand that is stored to is never a constant, so these are left alone
+        Expr::Constant(_)
+        | Expr::FloatConstant(_)
+        | Expr::Var(_)
+        | Expr::PrefixInc(_)
+        | Expr::PrefixDec(_)
+        | Expr::PostfixInc(_)
+        | Expr::PostfixDec(_) => expr.clone(),
+        Expr::Unary(op, operand) => {
+            let operand = fold_expr(operand);
+            match operand {
+                Expr::Constant(value) => Expr::Constant(fold_unary(op, value)),
+                operand => Expr::Unary(op.clone(), Box::new(operand)),
+            }
+        }
+        Expr::Binary(op, left, right) => {
+            let left = fold_expr(left);
+            let right = fold_expr(right);
+            // The right operand of '&&' and '||' is never evaluated once the left one decides
+            // the result, so the whole expression folds even if the right one isn't constant
+            match (op, &left) {
+                (BinaryOp::And, Expr::Constant(0)) => return Expr::Constant(0),
+                (BinaryOp::Or, Expr::Constant(value)) if *value != 0 => return Expr::Constant(1),
+                _ => {}
+            }
+            if let (Expr::Constant(a), Expr::Constant(b)) = (&left, &right) {
+                if let Some(value) = fold_binary(op, *a, *b) {
+                    return Expr::Constant(value);
+                }
+            }
+            Expr::Binary(op.clone(), Box::new(left), Box::new(right))
+        }
+        Expr::Comma(left, right) => {
+            let left = fold_expr(left);
+            let right = fold_expr(right);
+            // A constant left operand has no side effects, so only the right one matters
+            match left {
+                Expr::Constant(_) => right,
+                left => Expr::Comma(Box::new(left), Box::new(right)),
+            }
+        }
+        Expr::Assign(target, value) => Expr::Assign(target.clone(), Box::new(fold_expr(value))),
+        Expr::Conditional { cond, then, else_ } => {
+            let cond = fold_expr(cond);
+            let then = fold_expr(then);
+            let else_ = fold_expr(else_);
+            // Only the chosen branch would ever run, so a constant condition selects it outright,
+            // still converted to 'double' when the other branch would have made the result one
+            let has_double = value_type(&then) == Type::Double || value_type(&else_) == Type::Double;
+            let chosen = match cond {
+                Expr::Constant(0) => else_,
+                Expr::Constant(_) => then,
+                cond => return Expr::Conditional { cond: Box::new(cond), then: Box::new(then), else_: Box::new(else_) },
+            };
+            if has_double && value_type(&chosen) == Type::Int { Expr::Cast(Type::Double, Box::new(chosen)) } else { chosen }
+        }
+        Expr::Call { name, args } => Expr::Call { name: name.clone(), args: args.iter().map(fold_expr).collect() },
+        Expr::Subscript { base, index } => Expr::Subscript { base: Box::new(fold_expr(base)), index: Box::new(fold_expr(index)) },
+        Expr::Deref(operand) => Expr::Deref(Box::new(fold_expr(operand))),
+        // Type checking has already ruled out 'void' as a 'sizeof' operand
+        Expr::SizeOf(operand) => Expr::Constant(value_type(operand).size().unwrap()),
+        Expr::SizeOfType(ty) => ty.size().map_or_else(|| expr.clone(), Expr::Constant),
+        // Casting an 'int' constant to 'int' leaves it as it is
+        Expr::Cast(ty, operand) => match (ty, fold_expr(operand)) {
+            (Type::Int, Expr::Constant(value)) => Expr::Constant(value),
+            (ty, operand) => Expr::Cast(ty.clone(), Box::new(operand)),
+        },
+        Expr::Member { base, field } => Expr::Member { base: Box::new(fold_expr(base)), field: field.clone() },
+        Expr::PtrMember { base, field } => Expr::PtrMember { base: Box::new(fold_expr(base)), field: field.clone() },
+        Expr::AddrOf(operand) => Expr::AddrOf(operand.clone()), // The operand names an object, so there is nothing to fold
+    }
+}
+
+// Evaluate a unary operator on a constant, wrapping on overflow like the generated code
+fn fold_unary(op: &UnaryOp, value: i32) -> i32 {
+    match op {
+        UnaryOp::Negate => value.wrapping_neg(),
+        UnaryOp::Complement => !value,
+        UnaryOp::Not => (value == 0) as i32,
+    }
+}
+
+// Evaluate a binary operator on two constants, or `None` when the result is undefined
+// (division by zero, 'INT_MIN / -1', or a shift count outside 0..32)
+fn fold_binary(op: &BinaryOp, a: i32, b: i32) -> Option<i32> {
+    let value = match op {
+        BinaryOp::Add => a.wrapping_add(b),
+        BinaryOp::Subtract => a.wrapping_sub(b),
+        BinaryOp::Multiply => a.wrapping_mul(b),
+        BinaryOp::Divide => a.checked_div(b)?,
+        BinaryOp::Remainder => a.checked_rem(b)?,
+        BinaryOp::ShiftLeft => a.checked_shl(u32::try_from(b).ok()?)?,
+        BinaryOp::ShiftRight => a.checked_shr(u32::try_from(b).ok()?)?,
+        BinaryOp::Less => (a < b) as i32,
+        BinaryOp::LessEqual => (a <= b) as i32,
+        BinaryOp::Greater => (a > b) as i32,
+        BinaryOp::GreaterEqual => (a >= b) as i32,
+        BinaryOp::Equal => (a == b) as i32,
+        BinaryOp::NotEqual => (a != b) as i32,
+        BinaryOp::BitAnd => a & b,
+        BinaryOp::BitXor => a ^ b,
+        BinaryOp::BitOr => a | b,
+        BinaryOp::And => (a != 0 && b != 0) as i32,
+        BinaryOp::Or => (a != 0 || b != 0) as i32,
+    };
+    Some(value)
+}
+
+// Drop instructions that do nothing: moves from an operand to itself and a push
+// immediately undone by a pop into the same register. Every other instruction is kept.
+pub fn peephole(instructions: &[Instruction]) -> Vec<Instruction> {
+    let mut optimized: Vec<Instruction> = Vec::with_capacity(instructions.len());
+    for instruction in instructions {
+        match instruction {
+            Instruction::Mov(src, dst) if src == dst => continue, // Self-move
+            Instruction::MovQ(src, dst) if src == dst => continue, // Self-move
+            Instruction::Pop(reg) if optimized.last() == Some(&Instruction::Push(reg.clone())) => {
+                optimized.pop(); // The pair leaves the register and the stack as they were
+            }
+            _ => optimized.push(instruction.clone()),
+        }
+    }
+    optimized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::{Operand, Reg};
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    // Fold the expression of 'return <expr>;' inside 'main'
+    fn fold_return(expr: &str) -> Expr {
+        let program = parse(tokenize(&format!("int main(void) {{ return {}; }}", expr)).unwrap()).unwrap();
+        match fold_constants(&program).functions.remove(0).body.remove(0) {
+            BlockItem::Statement(Statement::Return(Some(expr))) => expr,
+            other => panic!("expected a return statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn constant_arithmetic_folds_to_one_constant() {
+        assert!(matches!(fold_return("2 + 3 * 4"), Expr::Constant(14)));
+        assert!(matches!(fold_return("(1 << 4) | 3 ^ 1"), Expr::Constant(18)));
+        assert!(matches!(fold_return("-~5 != 6 || 0"), Expr::Constant(0)));
+        assert!(matches!(fold_return("1 < 2 ? 10 : 20"), Expr::Constant(10)));
+    }
+
+    #[test]
+    fn undefined_operations_are_left_unfolded() {
+        assert!(matches!(fold_return("1 / 0"), Expr::Binary(BinaryOp::Divide, _, _)));
+        assert!(matches!(fold_return("1 % 0"), Expr::Binary(BinaryOp::Remainder, _, _)));
+        assert!(matches!(fold_return("(-2147483647 - 1) / -1"), Expr::Binary(BinaryOp::Divide, _, _)));
+        assert!(matches!(fold_return("1 << 32"), Expr::Binary(BinaryOp::ShiftLeft, _, _)));
+        assert!(matches!(fold_return("1 >> -1"), Expr::Binary(BinaryOp::ShiftRight, _, _)));
+    }
+
+    #[test]
+    fn operands_still_fold_around_an_unfoldable_operation() {
+        let Expr::Binary(BinaryOp::Divide, left, right) = fold_return("(2 + 3) / (1 - 1)") else { panic!("expected a division") };
+        assert!(matches!(*left, Expr::Constant(5)));
+        assert!(matches!(*right, Expr::Constant(0)));
+    }
+
+    #[test]
+    fn short_circuit_folds_without_a_constant_right_operand() {
+        assert!(matches!(fold_return("0 && x"), Expr::Constant(0)));
+        assert!(matches!(fold_return("2 || x"), Expr::Constant(1)));
+        assert!(matches!(fold_return("1 && x"), Expr::Binary(BinaryOp::And, _, _)));
+    }
+
+    #[test]
+    fn peephole_drops_self_moves() {
+        let eax = Operand::Register(Reg::Eax);
+        let instructions = vec![
+            Instruction::Mov(Operand::Imm(1), eax.clone()),
+            Instruction::Mov(eax.clone(), eax.clone()),
+            Instruction::MovQ(Reg::Rbp, Reg::Rbp),
+            Instruction::Ret,
+        ];
+        assert_eq!(peephole(&instructions), vec![Instruction::Mov(Operand::Imm(1), eax), Instruction::Ret]);
+    }
+
+    #[test]
+    fn peephole_drops_a_push_undone_by_a_pop() {
+        let instructions = vec![Instruction::Push(Reg::Eax), Instruction::Pop(Reg::Eax), Instruction::Ret];
+        assert_eq!(peephole(&instructions), vec![Instruction::Ret]);
+    }
+
+    #[test]
+    fn peephole_keeps_instructions_with_effects() {
+        let eax = Operand::Register(Reg::Eax);
+        let ecx = Operand::Register(Reg::Ecx);
+        let instructions = vec![
+            Instruction::Push(Reg::Eax),
+            Instruction::Mov(Operand::Imm(2), eax.clone()),
+            Instruction::Pop(Reg::Eax),
+            Instruction::Push(Reg::Eax),
+            Instruction::Pop(Reg::Ecx),
+            Instruction::Mov(eax, ecx),
+        ];
+        assert_eq!(peephole(&instructions), instructions);
+    }
+}
@@ -0,0 +1,950 @@
+// Recursive-descent parser: turns the lexer's token stream into a Program AST
+use crate::ast::{BinaryOp, BlockItem, Declaration, Expr, ForInit, Function, Program, Statement, StorageClass, Type, UnaryOp}; // Import the AST node types
+use crate::lexer::{Spanned, Token}; // Import the lexer's token types
+
+// Parse a full token stream into a program, requiring every token to be consumed. An error
+// carries the position it points at along with its message.
+pub fn parse(tokens: Vec<Spanned<Token>>) -> Result<Program, Spanned<String>> {
+    let mut parser = Parser { tokens, pos: 0 };
+    parser.parse_program() // Stops only at the end of the tokens, so all of them are consumed
+}
+
+// Cursor over the token stream with single-token lookahead
+struct Parser {
+    tokens: Vec<Spanned<Token>>, // Tokens produced by the lexer
+    pos: usize, // Index of the next token to consume
+}
+
+impl Parser {
+    // Look at the next token without consuming it
+    fn peek(&self) -> Option<&Spanned<Token>> {
+        self.tokens.get(self.pos)
+    }
+
+    // Consume and return the next token
+    fn advance(&mut self) -> Option<Spanned<Token>> {
+        let token = self.tokens.get(self.pos).cloned();
+        if token.is_some() {
+            self.pos += 1; // Move past the token
+        }
+        token
+    }
+
+    // Build an error naming what was expected and the token actually found
+    fn error_at(&self, found: &Spanned<Token>, expected: &str) -> Spanned<String> {
+        let value = format!(
+            "Parse Error: expected {} but found {} at line {}, column {}",
+            expected, found.value, found.line, found.col
+        );
+        Spanned { value, line: found.line, col: found.col }
+    }
+
+    // Build an error for running out of tokens while something was still expected, placed at
+    // the last token since nothing follows it
+    fn error_at_eof(&self, expected: &str) -> Spanned<String> {
+        let (line, col) = self.tokens.last().map_or((1, 1), |token| (token.line, token.col));
+        Spanned { value: format!("Parse Error: expected {} but found end of file", expected), line, col }
+    }
+
+    // Consume the next token, which must be exactly `expected`
+    fn expect(&mut self, expected: Token) -> Result<(), Spanned<String>> {
+        match self.advance() {
+            Some(token) if token.value == expected => Ok(()),
+            Some(token) => Err(self.error_at(&token, &expected.to_string())),
+            None => Err(self.error_at_eof(&expected.to_string())),
+        }
+    }
+
+    // Consume the next token, which must be an identifier, and return its name
+    fn expect_identifier(&mut self) -> Result<String, Spanned<String>> {
+        match self.advance() {
+            Some(Spanned { value: Token::Identifier(name), .. }) => Ok(name),
+            Some(token) => Err(self.error_at(&token, "an identifier")),
+            None => Err(self.error_at_eof("an identifier")),
+        }
+    }
+
+    // <program> ::= <top-level> { <top-level> }
+    // <top-level> ::= <function> | <declaration>
+    fn parse_program(&mut self) -> Result<Program, Spanned<String>> {
+        // An empty file (or one holding only comments) has no function to define, and with no
+        // tokens at all the error points at the start of the file
+        if self.peek().is_none() {
+            let value = "Parse Error: expected function definition, found end of file".to_string();
+            return Err(Spanned { value, line: 1, col: 1 });
+        }
+        let mut program = Program { functions: Vec::new(), variables: Vec::new() };
+        // Every token after a function or declaration must begin another one
+        while self.peek().is_some() {
+            if self.at_function() {
+                program.functions.push(self.parse_function()?);
+            } else {
+                program.variables.push(self.parse_declaration()?);
+            }
+        }
+        Ok(program)
+    }
+
+    // Whether the next tokens start a function: after the optional storage class, the type and
+    // the name, a function has '(' where a variable has ';' or '='. Tokens that end right after
+    // the name are a variable missing its ';'.
+    fn at_function(&self) -> bool {
+        let storage = matches!(self.peek(), Some(Spanned { value: Token::StaticKeyword | Token::ExternKeyword, .. }));
+        let name = self.pos + usize::from(storage) + 1;
+        match self.tokens.get(name + 1) {
+            Some(Spanned { value: Token::Semicolon | Token::Assign, .. }) => false,
+            Some(_) => true,
+            None => !matches!(self.tokens.get(name), Some(Spanned { value: Token::Identifier(_), .. })),
+        }
+    }
+
+    // <function> ::= [ <storage-class> ] <type> <identifier> "(" [ <param-list> ] ")" <block>
+    fn parse_function(&mut self) -> Result<Function, Spanned<String>> {
+        let storage = self.parse_storage_class();
+        let (return_type, _, _) = self.parse_type()?;
+        let name = self.expect_identifier()?;
+        self.expect(Token::OpenParenthesis)?;
+        let unspecified_params = matches!(self.peek(), Some(Spanned { value: Token::CloseParenthesis, .. }));
+        let params = if unspecified_params { Vec::new() } else { self.parse_params()? };
+        self.expect(Token::CloseParenthesis)?;
+        let body = self.parse_block()?;
+        Ok(Function { storage, return_type, name, params, unspecified_params, body })
+    }
+
+    // <param-list> ::= "void" | <type> <identifier> { "," <type> <identifier> }
+    // A lone 'void' means the function takes no parameters
+    fn parse_params(&mut self) -> Result<Vec<(Type, String)>, Spanned<String>> {
+        let is_lone_void = matches!(self.peek(), Some(Spanned { value: Token::VoidKeyword, .. }))
+            && matches!(self.tokens.get(self.pos + 1), Some(Spanned { value: Token::CloseParenthesis, .. }));
+        if is_lone_void {
+            self.advance(); // Consume 'void'
+            return Ok(Vec::new());
+        }
+        let mut params = Vec::new();
+        loop {
+            let (ty, line, col) = self.parse_type()?;
+            let name = self.expect_identifier()?;
+            if ty == Type::Void {
+                let value = format!("Parse Error: parameter '{}' cannot have type 'void' at line {}, column {}", name, line, col);
+                return Err(Spanned { value, line, col });
+            }
+            params.push((ty, name));
+            if !matches!(self.peek(), Some(Spanned { value: Token::Comma, .. })) {
+                return Ok(params);
+            }
+            self.advance(); // Consume ','
+        }
+    }
+
+    // <type> ::= "int" | "void" | "double"
+    // Returns the type with the position of its keyword
+    fn parse_type(&mut self) -> Result<(Type, usize, usize), Spanned<String>> {
+        match self.advance() {
+            Some(Spanned { value: Token::IntKeyword, line, col }) => Ok((Type::Int, line, col)),
+            Some(Spanned { value: Token::VoidKeyword, line, col }) => Ok((Type::Void, line, col)),
+            Some(Spanned { value: Token::DoubleKeyword, line, col }) => Ok((Type::Double, line, col)),
+            Some(token) => Err(self.error_at(&token, "a type")),
+            None => Err(self.error_at_eof("a type")),
+        }
+    }
+
+    // <storage-class> ::= "static" | "extern"
+    // Consumes the storage class if the next token is one; they are recorded but not yet enforced
+    fn parse_storage_class(&mut self) -> Option<StorageClass> {
+        let storage = match self.peek()?.value {
+            Token::StaticKeyword => StorageClass::Static,
+            Token::ExternKeyword => StorageClass::Extern,
+            _ => return None,
+        };
+        self.advance(); // Consume the keyword
+        Some(storage)
+    }
+
+    // <block> ::= "{" { <block-item> } "}"
+    fn parse_block(&mut self) -> Result<Vec<BlockItem>, Spanned<String>> {
+        self.expect(Token::OpenBrace)?;
+        let mut items = Vec::new();
+        // Keep reading items until the closing brace, even after a return
+        while !matches!(self.peek(), Some(Spanned { value: Token::CloseBrace, .. })) {
+            items.push(self.parse_block_item()?);
+        }
+        self.expect(Token::CloseBrace)?;
+        Ok(items)
+    }
+
+    // <block-item> ::= <declaration> | <statement>
+    fn parse_block_item(&mut self) -> Result<BlockItem, Spanned<String>> {
+        match self.peek() {
+            Some(Spanned { value: Token::IntKeyword | Token::DoubleKeyword | Token::StaticKeyword | Token::ExternKeyword, .. }) => {
+                Ok(BlockItem::Declaration(self.parse_declaration()?))
+            }
+            _ => Ok(BlockItem::Statement(self.parse_statement()?)),
+        }
+    }
+
+    // <statement> ::= "return" <exp> ";" | <exp> ";" | <block>
+    //               | "if" "(" <exp> ")" <statement> [ "else" <statement> ]
+    //               | "while" "(" <exp> ")" <statement>
+    //               | "for" "(" <for-init> [ <exp> ] ";" [ <exp> ] ")" <statement>
+    //               | "break" ";" | "continue" ";"
+    //               | "goto" <identifier> ";" | <identifier> ":" <statement>
+    //               | "do" <statement> "while" "(" <exp> ")" ";"
+    //               | "switch" "(" <exp> ")" <statement>
+    //               | "case" <exp> ":" <statement> | "default" ":" <statement>
+    fn parse_statement(&mut self) -> Result<Statement, Spanned<String>> {
+        // An identifier directly followed by ':' labels the statement after it
+        if let (Some(Spanned { value: Token::Identifier(label), .. }), Some(Spanned { value: Token::Colon, .. })) =
+            (self.peek(), self.tokens.get(self.pos + 1))
+        {
+            let label = label.clone();
+            self.advance(); // Consume the label name
+            self.advance(); // Consume ':'
+            return Ok(Statement::Label(label, Box::new(self.parse_statement()?)));
+        }
+        match self.peek() {
+            Some(Spanned { value: Token::GotoKeyword, .. }) => {
+                self.advance(); // Consume 'goto'
+                let label = self.expect_identifier()?;
+                self.expect(Token::Semicolon)?;
+                Ok(Statement::Goto(label))
+            }
+            Some(Spanned { value: Token::SwitchKeyword, .. }) => {
+                self.advance(); // Consume 'switch'
+                self.expect(Token::OpenParenthesis)?;
+                let value = self.parse_expr(0)?;
+                self.expect(Token::CloseParenthesis)?;
+                let body = Box::new(self.parse_statement()?);
+                Ok(Statement::Switch { value, body })
+            }
+            Some(Spanned { value: Token::CaseKeyword, .. }) => {
+                self.advance(); // Consume 'case'
+                // A case value is a conditional expression; C doesn't allow an assignment there
+                let value = self.parse_expr(CONDITIONAL_PREC)?;
+                self.expect(Token::Colon)?;
+                Ok(Statement::Case(value, Box::new(self.parse_statement()?)))
+            }
+            Some(Spanned { value: Token::DefaultKeyword, .. }) => {
+                self.advance(); // Consume 'default'
+                self.expect(Token::Colon)?;
+                Ok(Statement::Default(Box::new(self.parse_statement()?)))
+            }
+            Some(Spanned { value: Token::BreakKeyword, .. }) => {
+                self.advance(); // Consume 'break'
+                self.expect(Token::Semicolon)?;
+                Ok(Statement::Break)
+            }
+            Some(Spanned { value: Token::ContinueKeyword, .. }) => {
+                self.advance(); // Consume 'continue'
+                self.expect(Token::Semicolon)?;
+                Ok(Statement::Continue)
+            }
+            Some(Spanned { value: Token::OpenBrace, .. }) => Ok(Statement::Block(self.parse_block()?)),
+            Some(Spanned { value: Token::WhileKeyword, .. }) => {
+                self.advance(); // Consume 'while'
+                self.expect(Token::OpenParenthesis)?;
+                let cond = self.parse_expr(0)?;
+                self.expect(Token::CloseParenthesis)?;
+                let body = Box::new(self.parse_statement()?);
+                Ok(Statement::While { cond, body })
+            }
+            Some(Spanned { value: Token::DoKeyword, .. }) => {
+                self.advance(); // Consume 'do'
+                let body = Box::new(self.parse_statement()?);
+                self.expect(Token::WhileKeyword)?;
+                self.expect(Token::OpenParenthesis)?;
+                let cond = self.parse_expr(0)?;
+                self.expect(Token::CloseParenthesis)?;
+                self.expect(Token::Semicolon)?;
+                Ok(Statement::DoWhile { body, cond })
+            }
+            Some(Spanned { value: Token::ForKeyword, .. }) => {
+                self.advance(); // Consume 'for'
+                self.expect(Token::OpenParenthesis)?;
+                // <for-init> ::= <declaration> | [ <exp> ] ";"
+                let init = match self.peek() {
+                    Some(Spanned { value: Token::IntKeyword | Token::DoubleKeyword | Token::StaticKeyword | Token::ExternKeyword, .. }) => {
+                        Some(ForInit::Declaration(self.parse_declaration()?)) // Consumes its own ';'
+                    }
+                    _ => {
+                        let init = self.parse_optional_expr(Token::Semicolon)?.map(ForInit::Expression);
+                        self.expect(Token::Semicolon)?;
+                        init
+                    }
+                };
+                let cond = self.parse_optional_expr(Token::Semicolon)?;
+                self.expect(Token::Semicolon)?;
+                let post = self.parse_optional_expr(Token::CloseParenthesis)?;
+                self.expect(Token::CloseParenthesis)?;
+                let body = Box::new(self.parse_statement()?);
+                Ok(Statement::For { init, cond, post, body })
+            }
+            Some(Spanned { value: Token::IfKeyword, .. }) => {
+                self.advance(); // Consume 'if'
+                self.expect(Token::OpenParenthesis)?;
+                let cond = self.parse_expr(0)?;
+                self.expect(Token::CloseParenthesis)?;
+                let then = Box::new(self.parse_statement()?);
+                // Checking for 'else' right after the inner statement binds a dangling else to the nearest if
+                let else_branch = if matches!(self.peek(), Some(Spanned { value: Token::ElseKeyword, .. })) {
+                    self.advance(); // Consume 'else'
+                    Some(Box::new(self.parse_statement()?))
+                } else {
+                    None
+                };
+                Ok(Statement::If { cond, then, else_branch })
+            }
+            Some(Spanned { value: Token::ReturnKeyword, .. }) => {
+                self.advance(); // Consume 'return'
+                let expr = self.parse_optional_expr(Token::Semicolon)?; // A 'void' function returns no value
+                self.expect(Token::Semicolon)?;
+                Ok(Statement::Return(expr))
+            }
+            _ => {
+                let expr = self.parse_expr(0)?;
+                self.expect(Token::Semicolon)?;
+                Ok(Statement::Expression(expr))
+            }
+        }
+    }
+
+    // Parse an expression unless the next token is `end`, which marks an empty clause
+    fn parse_optional_expr(&mut self, end: Token) -> Result<Option<Expr>, Spanned<String>> {
+        match self.peek() {
+            Some(token) if token.value == end => Ok(None),
+            _ => Ok(Some(self.parse_expr(0)?)),
+        }
+    }
+
+    // <declaration> ::= [ <storage-class> ] "int" <identifier> [ "=" <exp> ] ";"
+    fn parse_declaration(&mut self) -> Result<Declaration, Spanned<String>> {
+        let storage = self.parse_storage_class();
+        // Every variable is an 'int' so far; 'double' values only come from constants and casts
+        if let Some(&Spanned { value: Token::DoubleKeyword, line, col }) = self.peek() {
+            let value = format!("Parse Error: variables of type 'double' are not supported yet at line {}, column {}", line, col);
+            return Err(Spanned { value, line, col });
+        }
+        self.expect(Token::IntKeyword)?;
+        let name = self.expect_identifier()?;
+        let init = if matches!(self.peek(), Some(Spanned { value: Token::Assign, .. })) {
+            self.advance(); // Consume '='
+            Some(self.parse_expr(ASSIGN_PREC)?) // A comma here would start the next declarator
+        } else {
+            None
+        };
+        self.expect(Token::Semicolon)?;
+        Ok(Declaration { storage, name, init })
+    }
+
+    // <exp> ::= <factor> | <exp> <binop> <exp> | <exp> "=" <exp> | <exp> "?" <exp> ":" <exp>
+    //         | <exp> "," <exp>
+    // Precedence climbing: only operators binding at least as tightly as `min_prec` are
+    // absorbed here, and the right operand is parsed one level higher so that operators
+    // of equal precedence associate to the left. Assignment and the conditional are the
+    // exceptions: their right operand is parsed at the same level so that `a = b = c` and
+    // `a ? b : c ? d : e` nest to the right.
+    fn parse_expr(&mut self, min_prec: u8) -> Result<Expr, Spanned<String>> {
+        let mut left = self.parse_factor()?;
+        while let Some(next) = self.peek() {
+            if next.value == Token::Comma && min_prec == COMMA_PREC {
+                self.advance(); // Consume ','
+                let right = self.parse_expr(COMMA_PREC + 1)?;
+                left = Expr::Comma(Box::new(left), Box::new(right));
+                continue;
+            }
+            if next.value == Token::Assign && ASSIGN_PREC >= min_prec {
+                let assign = self.advance().unwrap(); // Consume '='
+                if !is_lvalue(&left) {
+                    let value = format!("Parse Error: invalid assignment target at line {}, column {}", assign.line, assign.col);
+                    return Err(Spanned { value, line: assign.line, col: assign.col });
+                }
+                let right = self.parse_expr(ASSIGN_PREC)?;
+                left = Expr::Assign(Box::new(left), Box::new(right));
+                continue;
+            }
+            if next.value == Token::Question && CONDITIONAL_PREC >= min_prec {
+                self.advance(); // Consume '?'
+                let then = self.parse_expr(0)?; // Anything may appear between '?' and ':'
+                self.expect(Token::Colon)?;
+                let else_ = self.parse_expr(CONDITIONAL_PREC)?;
+                left = Expr::Conditional { cond: Box::new(left), then: Box::new(then), else_: Box::new(else_) };
+                continue;
+            }
+            let Some((op, prec)) = binary_operator(&next.value) else {
+                break; // Not an infix operator, so the expression ends here
+            };
+            if prec < min_prec {
+                break; // The operator belongs to an enclosing expression
+            }
+            self.advance(); // Consume the operator
+            let right = self.parse_expr(prec + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    // <factor> ::= <unop> <factor> | "*" <factor> | "&" <factor> | "++" <factor> | "--" <factor>
+    //            | "sizeof" <factor> | "sizeof" "(" <type> ")" | "(" <type> ")" <factor> | <postfix-exp>
+    // In prefix position '*' and '&' are dereference and address-of, not multiply and bitwise and
+    fn parse_factor(&mut self) -> Result<Expr, Spanned<String>> {
+        if self.at_type_name() {
+            self.advance(); // Consume '('
+            let (ty, _, _) = self.parse_type()?;
+            self.expect(Token::CloseParenthesis)?;
+            return Ok(Expr::Cast(ty, Box::new(self.parse_factor()?)));
+        }
+        match self.peek() {
+            Some(Spanned { value: Token::SizeofKeyword, .. }) => {
+                self.advance(); // Consume 'sizeof'
+                if self.at_type_name() {
+                    self.advance(); // Consume '('
+                    let (ty, _, _) = self.parse_type()?;
+                    self.expect(Token::CloseParenthesis)?;
+                    return Ok(Expr::SizeOfType(ty));
+                }
+                return Ok(Expr::SizeOf(Box::new(self.parse_factor()?)));
+            }
+            Some(Spanned { value: Token::Increment | Token::Decrement, .. }) => {
+                let token = self.advance().unwrap(); // Consume '++' or '--'
+                let operand = Box::new(self.parse_factor()?);
+                check_step_operand(&operand, &token)?;
+                return Ok(if token.value == Token::Increment { Expr::PrefixInc(operand) } else { Expr::PrefixDec(operand) });
+            }
+            Some(Spanned { value: Token::Asterisk, .. }) => {
+                self.advance(); // Consume '*'
+                return Ok(Expr::Deref(Box::new(self.parse_factor()?)));
+            }
+            Some(&Spanned { value: Token::Ampersand, line, col }) => {
+                self.advance(); // Consume '&'
+                let operand = self.parse_factor()?;
+                if !is_lvalue(&operand) {
+                    let value = format!("Parse Error: cannot take the address of this expression at line {}, column {}", line, col);
+                    return Err(Spanned { value, line, col });
+                }
+                return Ok(Expr::AddrOf(Box::new(operand)));
+            }
+            _ => {}
+        }
+        let op = match self.peek() {
+            Some(Spanned { value: Token::Minus, .. }) => UnaryOp::Negate,
+            Some(Spanned { value: Token::Tilde, .. }) => UnaryOp::Complement,
+            Some(Spanned { value: Token::LogicalNot, .. }) => UnaryOp::Not,
+            _ => return self.parse_postfix(),
+        };
+        self.advance(); // Consume the operator
+        Ok(Expr::Unary(op, Box::new(self.parse_factor()?)))
+    }
+
+    // Whether the next tokens are '(' and a type keyword, which start a parenthesized type name
+    // rather than a parenthesized expression
+    fn at_type_name(&self) -> bool {
+        matches!(self.peek(), Some(Spanned { value: Token::OpenParenthesis, .. }))
+            && matches!(
+                self.tokens.get(self.pos + 1),
+                Some(Spanned { value: Token::IntKeyword | Token::VoidKeyword | Token::DoubleKeyword, .. })
+            )
+    }
+
+    // <postfix-exp> ::= <primary> { "[" <exp> "]" | "." <identifier> | "->" <identifier> | "++" | "--" }
+    // Postfix operators bind tighter than prefix ones, so '-a[i]' negates the element
+    fn parse_postfix(&mut self) -> Result<Expr, Spanned<String>> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            expr = match self.peek() {
+                Some(&Spanned { value: Token::OpenBracket, line, col }) => {
+                    let expected = format!("']' to close the '[' at line {}, column {}", line, col);
+                    self.advance(); // Consume '['
+                    let index = self.parse_expr(0)?;
+                    match self.advance() {
+                        Some(Spanned { value: Token::CloseBracket, .. }) => {}
+                        Some(token) => return Err(self.error_at(&token, &expected)),
+                        None => return Err(self.error_at_eof(&expected)),
+                    }
+                    Expr::Subscript { base: Box::new(expr), index: Box::new(index) }
+                }
+                Some(Spanned { value: Token::Dot, .. }) => {
+                    self.advance(); // Consume '.'
+                    Expr::Member { base: Box::new(expr), field: self.expect_identifier()? }
+                }
+                Some(Spanned { value: Token::Arrow, .. }) => {
+                    self.advance(); // Consume '->'
+                    Expr::PtrMember { base: Box::new(expr), field: self.expect_identifier()? }
+                }
+                Some(Spanned { value: Token::Increment | Token::Decrement, .. }) => {
+                    let token = self.advance().unwrap(); // Consume '++' or '--'
+                    check_step_operand(&expr, &token)?;
+                    let operand = Box::new(expr);
+                    if token.value == Token::Increment { Expr::PostfixInc(operand) } else { Expr::PostfixDec(operand) }
+                }
+                _ => return Ok(expr),
+            };
+        }
+    }
+
+    // <primary> ::= <int> | <float> | <identifier> | <identifier> "(" [ <argument-list> ] ")" | "(" <exp> ")"
+    fn parse_primary(&mut self) -> Result<Expr, Spanned<String>> {
+        match self.advance() {
+            Some(Spanned { value: Token::Constant(value), line, col }) => {
+                // The lexer accepts anything that fits in 64 bits, but every constant here is an 'int'
+                let value = i32::try_from(value).map_err(|_| {
+                    let value = format!("Parse Error: integer constant '{}' is out of range at line {}, column {}", value, line, col);
+                    Spanned { value, line, col }
+                })?;
+                Ok(Expr::Constant(value))
+            }
+            Some(Spanned { value: Token::FloatConstant(text), line, col }) => {
+                // The lexer only lets through valid spellings, but one may be too large for a 'double'
+                match text.parse::<f64>() {
+                    Ok(value) if value.is_finite() => Ok(Expr::FloatConstant(value)),
+                    _ => {
+                        let value = format!("Parse Error: floating-point constant '{}' is out of range at line {}, column {}", text, line, col);
+                        Err(Spanned { value, line, col })
+                    }
+                }
+            }
+            Some(Spanned { value: Token::Identifier(name), .. }) => {
+                // A name directly followed by '(' is a call rather than a variable
+                if matches!(self.peek(), Some(Spanned { value: Token::OpenParenthesis, .. })) {
+                    self.advance(); // Consume '('
+                    let args = self.parse_arguments()?;
+                    return Ok(Expr::Call { name, args });
+                }
+                Ok(Expr::Var(name))
+            }
+            Some(Spanned { value: Token::OpenParenthesis, line, col }) => {
+                let inner = self.parse_expr(0)?; // Grouping resets precedence
+                // Point back at the opening parenthesis so an unclosed group is easy to find
+                let expected = format!("')' to close the '(' at line {}, column {}", line, col);
+                match self.advance() {
+                    Some(Spanned { value: Token::CloseParenthesis, .. }) => Ok(inner),
+                    Some(token) => Err(self.error_at(&token, &expected)),
+                    None => Err(self.error_at_eof(&expected)),
+                }
+            }
+            Some(token) => Err(self.error_at(&token, "an expression")),
+            None => Err(self.error_at_eof("an expression")),
+        }
+    }
+
+    // <argument-list> ::= <exp> { "," <exp> }
+    // Parses the arguments of a call after its '(', through the closing ')'
+    fn parse_arguments(&mut self) -> Result<Vec<Expr>, Spanned<String>> {
+        let mut args = Vec::new();
+        if matches!(self.peek(), Some(Spanned { value: Token::CloseParenthesis, .. })) {
+            self.advance(); // Consume ')' of a call with no arguments
+            return Ok(args);
+        }
+        loop {
+            // After a ',' another argument must follow, so 'foo(1,)' fails here at the ')'
+            args.push(self.parse_expr(ASSIGN_PREC)?); // Commas separate arguments rather than forming a comma expression
+            match self.advance() {
+                Some(Spanned { value: Token::Comma, .. }) => continue,
+                Some(Spanned { value: Token::CloseParenthesis, .. }) => return Ok(args),
+                Some(token) => return Err(self.error_at(&token, "',' or ')'")),
+                None => return Err(self.error_at_eof("',' or ')'")),
+            }
+        }
+    }
+}
+
+// Whether an expression names an object that can be assigned to or have its address taken
+fn is_lvalue(expr: &Expr) -> bool {
+    matches!(expr, Expr::Var(_) | Expr::Subscript { .. } | Expr::Deref(_) | Expr::Member { .. } | Expr::PtrMember { .. })
+}
+
+// '++' and '--' store back into their operand, so it has to be an lvalue
+fn check_step_operand(operand: &Expr, token: &Spanned<Token>) -> Result<(), Spanned<String>> {
+    if is_lvalue(operand) {
+        return Ok(());
+    }
+    let value = format!("Parse Error: operand of {} is not assignable at line {}, column {}", token.value, token.line, token.col);
+    Err(Spanned { value, line: token.line, col: token.col })
+}
+
+// Precedence of the comma operator, the loosest of all
+const COMMA_PREC: u8 = 0;
+
+// Precedence of '=', which binds more loosely than every binary operator
+const ASSIGN_PREC: u8 = 1;
+
+// Precedence of '?:', between assignment and '||'
+const CONDITIONAL_PREC: u8 = 3;
+
+// Map a token to the binary operator it spells and that operator's precedence,
+// where a higher number binds more tightly
+fn binary_operator(token: &Token) -> Option<(BinaryOp, u8)> {
+    let op = match token {
+        Token::Asterisk => BinaryOp::Multiply,
+        Token::Slash => BinaryOp::Divide,
+        Token::Percent => BinaryOp::Remainder,
+        Token::Plus => BinaryOp::Add,
+        Token::Minus => BinaryOp::Subtract,
+        Token::ShiftLeft => BinaryOp::ShiftLeft,
+        Token::ShiftRight => BinaryOp::ShiftRight,
+        Token::Less => BinaryOp::Less,
+        Token::LessEqual => BinaryOp::LessEqual,
+        Token::Greater => BinaryOp::Greater,
+        Token::GreaterEqual => BinaryOp::GreaterEqual,
+        Token::EqualEqual => BinaryOp::Equal,
+        Token::NotEqual => BinaryOp::NotEqual,
+        Token::Ampersand => BinaryOp::BitAnd,
+        Token::Caret => BinaryOp::BitXor,
+        Token::Pipe => BinaryOp::BitOr,
+        Token::LogicalAnd => BinaryOp::And,
+        Token::LogicalOr => BinaryOp::Or,
+        _ => return None,
+    };
+    let prec = op.precedence();
+    Some((op, prec))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+
+    // Lex and parse a source that should be valid
+    fn parse_source(source: &str) -> Program {
+        parse(tokenize(source).unwrap()).unwrap()
+    }
+
+    // Lex and parse a source that should fail to parse and return the error message
+    fn parse_error(source: &str) -> String {
+        parse(tokenize(source).unwrap()).unwrap_err().value
+    }
+
+    // Parse a 'main' with the given body and return its block items
+    fn parse_body(body: &str) -> Vec<BlockItem> {
+        parse_source(&format!("int main(void) {{ {} }}", body)).functions.remove(0).body
+    }
+
+    // The first item in the body of the program's only function
+    fn first_item(program: &Program) -> &BlockItem {
+        &program.functions[0].body[0]
+    }
+
+    // Write an expression with every operator and its operands in parentheses, so a test
+    // can check how the parser nested it
+    fn shape(expr: &Expr) -> String {
+        match expr {
+            Expr::Constant(value) => value.to_string(),
+            Expr::FloatConstant(value) => value.to_string(),
+            Expr::Var(name) => name.clone(),
+            Expr::Unary(op, operand) => format!("({:?} {})", op, shape(operand)),
+            Expr::Binary(op, left, right) => format!("({:?} {} {})", op, shape(left), shape(right)),
+            Expr::Assign(target, value) => format!("(= {} {})", shape(target), shape(value)),
+            Expr::Call { name, args } => format!("(call {}{})", name, args.iter().map(|arg| format!(" {}", shape(arg))).collect::<String>()),
+            Expr::Conditional { cond, then, else_ } => format!("(? {} {} {})", shape(cond), shape(then), shape(else_)),
+            Expr::Subscript { base, index } => format!("([] {} {})", shape(base), shape(index)),
+            Expr::Deref(operand) => format!("(deref {})", shape(operand)),
+            Expr::AddrOf(operand) => format!("(addr {})", shape(operand)),
+            Expr::Member { base, field } => format!("(. {} {})", shape(base), field),
+            Expr::PtrMember { base, field } => format!("(-> {} {})", shape(base), field),
+            Expr::PrefixInc(operand) => format!("(pre++ {})", shape(operand)),
+            Expr::PrefixDec(operand) => format!("(pre-- {})", shape(operand)),
+            Expr::PostfixInc(operand) => format!("(post++ {})", shape(operand)),
+            Expr::PostfixDec(operand) => format!("(post-- {})", shape(operand)),
+            Expr::SizeOf(operand) => format!("(sizeof {})", shape(operand)),
+            Expr::SizeOfType(ty) => format!("(sizeof {:?})", ty),
+            Expr::Comma(left, right) => format!("(, {} {})", shape(left), shape(right)),
+            Expr::Cast(ty, operand) => format!("(cast {:?} {})", ty, shape(operand)),
+        }
+    }
+
+    // Parse 'return <expr>;' inside 'main' and return the shape of the expression
+    fn return_shape(expr: &str) -> String {
+        match parse_body(&format!("return {};", expr)).remove(0) {
+            BlockItem::Statement(Statement::Return(Some(expr))) => shape(&expr),
+            other => panic!("expected a return statement, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_a_function_returning_a_constant() {
+        let program = parse_source("int main(void) { return 2; }");
+        assert_eq!(program.functions.len(), 1);
+        assert_eq!(program.functions[0].name, "main");
+        assert!(matches!(first_item(&program), BlockItem::Statement(Statement::Return(Some(Expr::Constant(2))))));
+    }
+
+    #[test]
+    fn malformed_functions_name_the_expected_and_found_tokens() {
+        assert!(parse_error("int main(void) { return 2 }").contains("expected ';' but found '}'"));
+        assert!(parse_error("int 3(void) { return 0; }").contains("expected an identifier but found constant '3'"));
+        assert!(parse_error("int main(void) return 0;").contains("expected '{' but found 'return'"));
+        assert!(parse_error("main(void) { return 0; }").contains("expected a type but found identifier 'main'"));
+        assert!(parse_error("int main(void) { return 0; } }").contains("expected a type but found '}'"));
+    }
+
+    #[test]
+    fn unary_operators() {
+        assert_eq!(return_shape("-5"), "(Negate 5)");
+        assert_eq!(return_shape("~x"), "(Complement x)");
+        assert_eq!(return_shape("!x"), "(Not x)");
+        assert_eq!(return_shape("- -5"), "(Negate (Negate 5))");
+        assert_eq!(return_shape("~~x"), "(Complement (Complement x))");
+        assert!(parse_error("int main(void) { return -; }").contains("expected an expression but found ';'"));
+    }
+
+    #[test]
+    fn binary_operators_follow_precedence() {
+        assert_eq!(return_shape("1+2*3"), "(Add 1 (Multiply 2 3))");
+        assert_eq!(return_shape("(1+2)*3"), "(Multiply (Add 1 2) 3)");
+        assert_eq!(return_shape("1 < 2 == 3 > 4"), "(Equal (Less 1 2) (Greater 3 4))");
+        assert_eq!(return_shape("1 - 2 - 3"), "(Subtract (Subtract 1 2) 3)");
+        assert_eq!(return_shape("a || b && c << 1"), "(Or a (And b (ShiftLeft c 1)))");
+    }
+
+    #[test]
+    fn parenthesized_expressions() {
+        assert_eq!(return_shape("-(1 + 2)"), "(Negate (Add 1 2))");
+        let error = parse_error("int main(void) { return (1; }");
+        assert!(error.contains("expected ')' to close the '(' at line 1, column 25 but found ';'"), "{}", error);
+    }
+
+    #[test]
+    fn function_body_holds_every_statement() {
+        let body = parse_body("return 1; return 2;");
+        assert_eq!(body.len(), 2);
+        assert!(matches!(&body[1], BlockItem::Statement(Statement::Return(Some(Expr::Constant(2))))));
+    }
+
+    #[test]
+    fn declarations_with_and_without_initializers() {
+        let body = parse_body("int x = 5; int y;");
+        let BlockItem::Declaration(x) = &body[0] else { panic!("expected a declaration") };
+        assert_eq!(x.name, "x");
+        assert!(matches!(x.init, Some(Expr::Constant(5))));
+        let BlockItem::Declaration(y) = &body[1] else { panic!("expected a declaration") };
+        assert_eq!(y.name, "y");
+        assert!(y.init.is_none());
+        assert!(parse_error("int main(void) { int x = 5 return x; }").contains("expected ';' but found 'return'"));
+    }
+
+    #[test]
+    fn assignment_is_right_associative() {
+        assert_eq!(return_shape("x = y = 5"), "(= x (= y 5))");
+        assert!(parse_error("int main(void) { return 5 = x; }").contains("invalid assignment target"));
+    }
+
+    #[test]
+    fn if_statements() {
+        let body = parse_body("if (x) return 1; if (x) return 1; else return 2;");
+        assert!(matches!(&body[0], BlockItem::Statement(Statement::If { else_branch: None, .. })));
+        assert!(matches!(&body[1], BlockItem::Statement(Statement::If { else_branch: Some(_), .. })));
+    }
+
+    #[test]
+    fn dangling_else_binds_to_the_nearest_if() {
+        let body = parse_body("if (a) if (b) return 1; else return 2;");
+        let BlockItem::Statement(Statement::If { then, else_branch, .. }) = &body[0] else { panic!("expected an if") };
+        assert!(else_branch.is_none());
+        assert!(matches!(**then, Statement::If { else_branch: Some(_), .. }));
+    }
+
+    #[test]
+    fn while_and_for_loops() {
+        let body = parse_body("while (x < 10) { x = x + 1; } for (int i = 0; i < n; i = i + 1) { } for (;;) { }");
+        assert!(matches!(&body[0], BlockItem::Statement(Statement::While { cond: Expr::Binary(BinaryOp::Less, _, _), .. })));
+        let BlockItem::Statement(Statement::For { init, cond, post, .. }) = &body[1] else { panic!("expected a for loop") };
+        assert!(matches!(init, Some(ForInit::Declaration(declaration)) if declaration.name == "i"));
+        assert!(cond.is_some() && post.is_some());
+        assert!(matches!(&body[2], BlockItem::Statement(Statement::For { init: None, cond: None, post: None, .. })));
+    }
+
+    #[test]
+    fn break_and_continue() {
+        let body = parse_body("while (1) break; while (1) continue;");
+        assert!(matches!(&body[0], BlockItem::Statement(Statement::While { body, .. }) if matches!(**body, Statement::Break)));
+        assert!(matches!(&body[1], BlockItem::Statement(Statement::While { body, .. }) if matches!(**body, Statement::Continue)));
+        assert!(parse_error("int main(void) { while (1) break }").contains("expected ';' but found '}'"));
+    }
+
+    #[test]
+    fn blocks() {
+        let body = parse_body("{} { int a = 1; a = 2; } {{{ return 0; }}}");
+        assert!(matches!(&body[0], BlockItem::Statement(Statement::Block(items)) if items.is_empty()));
+        let BlockItem::Statement(Statement::Block(items)) = &body[1] else { panic!("expected a block") };
+        assert!(matches!(items[0], BlockItem::Declaration(_)));
+        assert!(matches!(items[1], BlockItem::Statement(Statement::Expression(Expr::Assign(_, _)))));
+        let mut depth = 0;
+        let mut statement = match &body[2] {
+            BlockItem::Statement(statement) => statement,
+            _ => panic!("expected a block"),
+        };
+        while let Statement::Block(items) = statement {
+            depth += 1;
+            statement = match &items[0] {
+                BlockItem::Statement(statement) => statement,
+                _ => panic!("expected a statement"),
+            };
+        }
+        assert_eq!(depth, 3);
+        assert!(matches!(statement, Statement::Return(_)));
+    }
+
+    #[test]
+    fn function_calls() {
+        assert_eq!(return_shape("foo()"), "(call foo)");
+        assert_eq!(return_shape("foo(1, 2)"), "(call foo 1 2)");
+        assert!(parse_error("int main(void) { return foo(1,); }").contains("expected an expression but found ')'"));
+    }
+
+    #[test]
+    fn multiple_functions() {
+        let program = parse_source("int helper(void) { return 1; } int main(void) { return helper(); }");
+        let names: Vec<&str> = program.functions.iter().map(|function| function.name.as_str()).collect();
+        assert_eq!(names, vec!["helper", "main"]);
+    }
+
+    #[test]
+    fn parameter_lists() {
+        let program = parse_source("int a(void) { return 0; } int b() { return 0; } int c(int x) { return x; } int d(int x, int y) { return x; }");
+        let functions = &program.functions;
+        assert!(functions[0].params.is_empty() && !functions[0].unspecified_params);
+        assert!(functions[1].params.is_empty() && functions[1].unspecified_params);
+        assert_eq!(functions[2].params, vec![(Type::Int, "x".to_string())]);
+        assert_eq!(functions[3].params, vec![(Type::Int, "x".to_string()), (Type::Int, "y".to_string())]);
+    }
+
+    #[test]
+    fn conditional_expressions() {
+        assert_eq!(return_shape("a ? b : c"), "(? a b c)");
+        assert_eq!(return_shape("a ? b : c ? d : e"), "(? a b (? c d e))");
+        assert!(parse_error("int main(void) { return 1 ? 2 3; }").contains("expected ':' but found constant '3'"));
+    }
+
+    #[test]
+    fn goto_and_labels() {
+        let body = parse_body("goto done; done: return 0;");
+        assert!(matches!(&body[0], BlockItem::Statement(Statement::Goto(label)) if label == "done"));
+        assert!(matches!(&body[1], BlockItem::Statement(Statement::Label(label, _)) if label == "done"));
+        assert!(parse_error("int main(void) { goto ; }").contains("expected an identifier but found ';'"));
+    }
+
+    #[test]
+    fn switch_with_cases_and_default() {
+        let body = parse_body("switch (x) { case 1: return 1; case 2: return 2; default: return 0; }");
+        let BlockItem::Statement(Statement::Switch { value: Expr::Var(name), body }) = &body[0] else { panic!("expected a switch") };
+        assert_eq!(name, "x");
+        let Statement::Block(items) = &**body else { panic!("expected a block") };
+        assert!(matches!(&items[0], BlockItem::Statement(Statement::Case(Expr::Constant(1), _))));
+        assert!(matches!(&items[1], BlockItem::Statement(Statement::Case(Expr::Constant(2), _))));
+        assert!(matches!(&items[2], BlockItem::Statement(Statement::Default(_))));
+    }
+
+    #[test]
+    fn subscripts() {
+        assert_eq!(return_shape("a[i]"), "([] a i)");
+        assert_eq!(return_shape("a[i][j + 1]"), "([] ([] a i) (Add j 1))");
+        assert!(parse_error("int main(void) { return a[1; }").contains("expected ']' to close the '['"));
+    }
+
+    #[test]
+    fn dereference_and_address_of() {
+        assert_eq!(return_shape("*p"), "(deref p)");
+        assert_eq!(return_shape("&x"), "(addr x)");
+        assert_eq!(return_shape("*&x"), "(deref (addr x))");
+        assert_eq!(return_shape("a * *p & b"), "(BitAnd (Multiply a (deref p)) b)");
+    }
+
+    #[test]
+    fn member_access() {
+        assert_eq!(return_shape("s.field"), "(. s field)");
+        assert_eq!(return_shape("p->field"), "(-> p field)");
+        assert_eq!(return_shape("a.b.c"), "(. (. a b) c)");
+        assert_eq!(return_shape("p->q->r"), "(-> (-> p q) r)");
+        assert_eq!(return_shape("p->a.b"), "(. (-> p a) b)");
+        assert_eq!(return_shape("-s.x"), "(Negate (. s x))");
+    }
+
+    #[test]
+    fn prefix_and_postfix_increments() {
+        assert_eq!(return_shape("++x"), "(pre++ x)");
+        assert_eq!(return_shape("x++"), "(post++ x)");
+        assert_eq!(return_shape("--x"), "(pre-- x)");
+        assert_eq!(return_shape("x--"), "(post-- x)");
+    }
+
+    #[test]
+    fn sizeof_types_and_expressions() {
+        assert_eq!(return_shape("sizeof(int)"), "(sizeof Int)");
+        assert_eq!(return_shape("sizeof x"), "(sizeof x)");
+        assert_eq!(return_shape("sizeof(1+2)"), "(sizeof (Add 1 2))");
+    }
+
+    #[test]
+    fn comma_operator() {
+        assert_eq!(return_shape("(1, 2, 3)"), "(, (, 1 2) 3)");
+        assert_eq!(return_shape("f(1, 2)"), "(call f 1 2)");
+        assert_eq!(return_shape("f((1, 2))"), "(call f (, 1 2))");
+    }
+
+    #[test]
+    fn empty_file_is_a_clear_error() {
+        assert!(tokenize("").unwrap().is_empty());
+        let error = parse(tokenize("").unwrap()).unwrap_err();
+        assert_eq!(error.value, "Parse Error: expected function definition, found end of file");
+        assert_eq!((error.line, error.col), (1, 1));
+        assert_eq!(parse_error("// nothing here\n"), "Parse Error: expected function definition, found end of file");
+    }
+
+    #[test]
+    fn do_while_loops() {
+        let body = parse_body("do x = x + 1; while (x < 3);");
+        let BlockItem::Statement(Statement::DoWhile { body, cond }) = &body[0] else { panic!("expected a do-while loop") };
+        assert!(matches!(**body, Statement::Expression(Expr::Assign(..))));
+        assert_eq!(shape(cond), "(Less x 3)");
+        assert!(parse_error("int main(void) { do x = 1; while (x) }").contains("expected ';' but found '}'"));
+    }
+
+
+    #[test]
+    fn casts_bind_tighter_than_binary_operators() {
+        assert_eq!(return_shape("(int)(3.0 / 2.0)"), "(cast Int (Divide 3 2))");
+        assert_eq!(return_shape("(double)x + 1"), "(Add (cast Double x) 1)");
+        assert!(parse_error("int main(void) { double d = 1.5; return 0; }").contains("variables of type 'double' are not supported yet"));
+    }
+
+
+    #[test]
+    fn static_declaration_records_its_storage_class() {
+        let program = parse_source("static int x;");
+        assert_eq!(program.variables.len(), 1);
+        assert_eq!(program.variables[0].name, "x");
+        assert_eq!(program.variables[0].storage, Some(StorageClass::Static));
+    }
+
+    #[test]
+    fn extern_declaration_records_its_storage_class() {
+        let program = parse_source("extern int y;");
+        assert_eq!(program.variables.len(), 1);
+        assert_eq!(program.variables[0].name, "y");
+        assert_eq!(program.variables[0].storage, Some(StorageClass::Extern));
+    }
+
+    #[test]
+    fn storage_class_is_recorded_on_functions() {
+        let program = parse_source("static int helper(void) { return 1; } int main(void) { return helper(); }");
+        assert_eq!(program.functions[0].storage, Some(StorageClass::Static));
+        assert_eq!(program.functions[1].storage, None);
+    }
+
+    #[test]
+    fn file_scope_declarations_sit_between_functions() {
+        let program = parse_source("int z = 1; int main(void) { return 0; } static int w;");
+        let names: Vec<&str> = program.variables.iter().map(|declaration| declaration.name.as_str()).collect();
+        assert_eq!(names, ["z", "w"]);
+        assert_eq!(shape(program.variables[0].init.as_ref().unwrap()), "1");
+        assert_eq!(program.functions[0].name, "main");
+        assert!(parse_error("static int x").contains("expected ';' but found end of file"));
+    }
+
+    #[test]
+    fn for_init_declaration_may_have_a_storage_class() {
+        let body = parse_body("for (static int i = 0; i < 3; i = i + 1) {}");
+        let BlockItem::Statement(Statement::For { init: Some(ForInit::Declaration(declaration)), .. }) = &body[0] else {
+            panic!("expected a for loop declaring its counter")
+        };
+        assert_eq!(declaration.storage, Some(StorageClass::Static));
+    }
+}
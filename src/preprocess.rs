@@ -0,0 +1,496 @@
+// Preprocessing: handles '#' directive lines before lexing and expands macros in the tokens after
+use crate::lexer::{join_errors, tokenize, Spanned, Token}; // Import the lexer, to turn replacement text into tokens
+use std::collections::HashMap; // Import map type for the macro table
+use std::fs; // Import file reading, for '#include'
+use std::path::{Path, PathBuf}; // Import path types, for finding included files
+
+// How deeply '#include' may nest before the preprocessor gives up, as in gcc
+const MAX_INCLUDE_DEPTH: usize = 200;
+
+// Directories searched for '#include <...>' files, and for '#include "..."' files not found next
+// to the including file. No headers ship with the compiler yet, so there are none.
+const SYSTEM_INCLUDE_DIRS: [&str; 0] = [];
+
+// The nine trigraphs: '??' followed by the first character stands for the second
+const TRIGRAPHS: [(char, char); 9] =
+    [('=', '#'), ('(', '['), ('/', '\\'), (')', ']'), ('\'', '^'), ('<', '{'), ('!', '|'), ('>', '}'), ('-', '~')];
+
+// The macros defined so far, and the warnings raised while reading the directives
+#[derive(Clone, Default)]
+pub struct Preprocessor {
+    macros: HashMap<String, Macro>, // Every defined macro, by name
+    definitions: Vec<(usize, String, Macro)>, // Each definition in order, with the output line it follows
+    warnings: Vec<String>,         // Warnings in source order; they never stop compilation
+    including: Vec<PathBuf>,       // The files being processed, outermost first, to catch include cycles
+    trigraphs: bool,               // Whether to replace trigraphs before reading each file
+}
+
+// An object-like macro, as written in its '#define'
+#[derive(Clone)]
+struct Macro {
+    text: String,       // The replacement as spelled, to tell a real redefinition from a repeated one
+    tokens: Vec<Token>, // The tokens an occurrence of the name is replaced with
+}
+
+// An '#ifdef' or '#ifndef' group that hasn't reached its '#endif' yet
+struct Conditional {
+    line: usize,     // Line of the opening directive, for reporting a missing '#endif'
+    enclosing: bool, // Whether the lines around the group are kept
+    taken: bool,     // Whether the condition held, which keeps the lines before any '#else'
+    in_else: bool,   // Whether the group's '#else' has been seen
+}
+
+impl Conditional {
+    // Whether the lines at this point in the group are kept
+    fn active(&self) -> bool {
+        self.enclosing && self.taken != self.in_else
+    }
+}
+
+impl Preprocessor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Define a macro given on the command line, as with '-D NAME=VALUE'
+    pub fn define(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if !is_identifier(name) {
+            return Err(format!("Error: Invalid macro name '{}' for '-D'.", name));
+        }
+        self.define_macro(name, value, "on the command line", 0) // Before the first line
+    }
+
+    // Turn trigraph replacement on or off; it is off by default, as in gcc
+    pub fn set_trigraphs(&mut self, enabled: bool) {
+        self.trigraphs = enabled;
+    }
+
+    // The warnings raised so far
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    // Run the directives in a source file read from `path`, returning the source without them.
+    // Each directive line is replaced with an empty one, so the tokens left keep their line
+    // numbers, except that lines after an '#include' are counted after the spliced-in ones.
+    pub fn process(&mut self, source: &str, path: &Path) -> Result<String, String> {
+        self.process_from(source, path, 1)
+    }
+
+    // Run the directives in a source file whose first line lands on output line `first_line`
+    fn process_from(&mut self, source: &str, path: &Path, first_line: usize) -> Result<String, String> {
+        // Canonical paths make two spellings of the same file compare equal
+        self.including.push(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()));
+        let replaced;
+        let source = if self.trigraphs {
+            replaced = replace_trigraphs(source); // Trigraphs may spell a directive's '#', so they go first
+            &replaced
+        } else {
+            source
+        };
+        let result = self.process_lines(source, path, first_line);
+        self.including.pop();
+        result
+    }
+
+    // Run the directives in each line of a file, keeping the other lines as they are
+    fn process_lines(&mut self, source: &str, path: &Path, first_line: usize) -> Result<String, String> {
+        let mut out = String::with_capacity(source.len());
+        let mut out_line = first_line; // The output line this source line lands on
+        let mut in_comment = false; // Whether the current line starts inside a '/* */' comment
+        let mut conditionals = Vec::new(); // The enclosing conditional groups, outermost first
+        for (i, line) in split_lines(source).enumerate() {
+            let start = out.len();
+            // Lines in a skipped group become empty, like directives, and their directives do
+            // nothing except keep track of nested groups
+            let active = conditionals.last().is_none_or(Conditional::active);
+            // A '#' only starts a directive as the first thing on a line outside a comment
+            match line.trim_start().strip_prefix('#') {
+                Some(directive) if !in_comment => {
+                    let (name, rest) = split_identifier(directive.trim_start());
+                    if !self.conditional(name, rest, i + 1, &mut conditionals)? && active {
+                        self.directive(directive, path, i + 1, out_line, &mut out)?;
+                    }
+                }
+                _ if active => out.push_str(line),
+                _ => {}
+            }
+            out.push('\n');
+            out_line += out[start..].matches('\n').count(); // An '#include' adds the lines it spliced in
+            in_comment = ends_in_comment(line, in_comment);
+        }
+        // Each file must close the groups it opens
+        if let Some(conditional) = conditionals.last() {
+            return Err(format!("Preprocessor Error: Missing '#endif' for the conditional at line {}", conditional.line));
+        }
+        Ok(out)
+    }
+
+    // Track the conditional directives '#ifdef', '#ifndef', '#else', and '#endif', returning
+    // whether `name` was one of them
+    fn conditional(&self, name: &str, rest: &str, line: usize, conditionals: &mut Vec<Conditional>) -> Result<bool, String> {
+        let enclosing = conditionals.last().is_none_or(Conditional::active);
+        match name {
+            "ifdef" | "ifndef" => {
+                let (macro_name, _) = split_identifier(rest.trim_start());
+                if macro_name.is_empty() {
+                    return Err(format!("Preprocessor Error: '#{}' needs a macro name at line {}", name, line));
+                }
+                let defined = self.macros.contains_key(macro_name);
+                conditionals.push(Conditional { line, enclosing, taken: defined == (name == "ifdef"), in_else: false });
+            }
+            "else" => match conditionals.last_mut() {
+                Some(conditional) if !conditional.in_else => conditional.in_else = true,
+                Some(conditional) => {
+                    return Err(format!(
+                        "Preprocessor Error: Second '#else' at line {} for the conditional at line {}",
+                        line, conditional.line
+                    ));
+                }
+                None => return Err(format!("Preprocessor Error: '#else' without '#ifdef' or '#ifndef' at line {}", line)),
+            },
+            "endif" => {
+                if conditionals.pop().is_none() {
+                    return Err(format!("Preprocessor Error: '#endif' without '#ifdef' or '#ifndef' at line {}", line));
+                }
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    // Run one directive, given the text after its '#', appending any text it produces. `line` is
+    // the directive's line in its own file, for messages, and `out_line` its line in the output.
+    fn directive(&mut self, text: &str, path: &Path, line: usize, out_line: usize, out: &mut String) -> Result<(), String> {
+        let (name, rest) = split_identifier(text.trim_start());
+        match name {
+            "" if rest.trim().is_empty() => Ok(()), // A lone '#' is a null directive and does nothing
+            "define" => self.define_directive(rest, line, out_line),
+            "include" => self.include_directive(rest, path, line, out_line, out),
+            _ => Err(format!("Preprocessor Error: Unknown directive '#{}' at line {}", text.trim(), line)),
+        }
+    }
+
+    // Define the macro in '#define NAME value'; the value may be empty
+    fn define_directive(&mut self, text: &str, line: usize, out_line: usize) -> Result<(), String> {
+        let (name, value) = split_identifier(text.trim_start());
+        if !is_identifier(name) {
+            return Err(format!("Preprocessor Error: '#define' needs a macro name at line {}", line));
+        }
+        if value.starts_with('(') {
+            return Err(format!("Preprocessor Error: Function-like macro '{}' is not supported yet at line {}", name, line));
+        }
+        self.define_macro(name, value, &format!("at line {}", line), out_line)
+    }
+
+    // Add a macro to the table; `place` says where it was defined, for messages, and the macro
+    // replaces its name only in the tokens after output line `out_line`
+    fn define_macro(&mut self, name: &str, value: &str, place: &str, out_line: usize) -> Result<(), String> {
+        let text = value.trim().to_string();
+        let tokens = tokenize(&text).map_err(|errors| {
+            format!("Preprocessor Error: Invalid replacement for macro '{}' {}\n{}", name, place, join_errors(&errors))
+        })?;
+        // Repeating a definition word for word is allowed; changing it is likely a mistake
+        if self.macros.get(name).is_some_and(|old| old.text != text) {
+            self.warnings.push(format!("warning: macro '{}' redefined {}", name, place));
+        }
+        let tokens = tokens.into_iter().map(|token| token.value).collect();
+        let definition = Macro { text, tokens };
+        self.definitions.push((out_line, name.to_string(), definition.clone()));
+        self.macros.insert(name.to_string(), definition);
+        Ok(())
+    }
+
+    // Splice in the preprocessed contents of the file named by '#include "file"' or
+    // '#include <file>'. A quoted name is looked for next to the including file first.
+    fn include_directive(&mut self, text: &str, path: &Path, line: usize, out_line: usize, out: &mut String) -> Result<(), String> {
+        let text = text.trim();
+        let (name, local) = match text.chars().next() {
+            Some('"') => (text[1..].split_once('"').map(|(name, _)| name), true),
+            Some('<') => (text[1..].split_once('>').map(|(name, _)| name), false),
+            _ => (None, false),
+        };
+        let Some(name) = name.filter(|name| !name.is_empty()) else {
+            return Err(format!("Preprocessor Error: '#include' expects \"FILE\" or <FILE> at line {}", line));
+        };
+
+        let mut dirs = Vec::new();
+        if local {
+            dirs.push(path.parent().unwrap_or(Path::new("")).to_path_buf());
+        }
+        dirs.extend(SYSTEM_INCLUDE_DIRS.iter().map(PathBuf::from));
+        let Some(found) = dirs.iter().map(|dir| dir.join(name)).find(|candidate| candidate.is_file()) else {
+            return Err(format!("Preprocessor Error: Included file '{}' not found at line {}", name, line));
+        };
+
+        // A file that is still being processed would include itself again, forever
+        let canonical = fs::canonicalize(&found).unwrap_or_else(|_| found.clone());
+        if self.including.contains(&canonical) {
+            return Err(format!("Preprocessor Error: '#include \"{}\"' at line {} forms an include cycle", name, line));
+        }
+        if self.including.len() >= MAX_INCLUDE_DEPTH {
+            return Err(format!("Preprocessor Error: '#include' nested more than {} levels deep at line {}", MAX_INCLUDE_DEPTH, line));
+        }
+
+        let contents = fs::read_to_string(&found)
+            .map_err(|_| format!("Preprocessor Error: Could not read included file '{}' at line {}", found.display(), line))?;
+        let spliced = self
+            .process_from(&contents, &found, out_line)
+            .map_err(|e| format!("{}\n  in '{}', included from '{}' at line {}", e, found.display(), path.display(), line))?;
+        out.push_str(spliced.strip_suffix('\n').unwrap_or(&spliced)); // The directive's own line ends it
+        Ok(())
+    }
+
+    // Replace every identifier that names a macro with the macro's tokens, each placed where
+    // the identifier was. A token sees the definitions made on the lines before it, so a name
+    // used before its '#define' is left alone and a redefinition applies from its line on. The
+    // replacement is expanded again, except for macros already being expanded, so a macro that
+    // mentions its own name stops after one step.
+    pub fn expand(&self, tokens: Vec<Spanned<Token>>) -> Vec<Spanned<Token>> {
+        let mut out = Vec::with_capacity(tokens.len());
+        let mut macros = HashMap::new(); // The macros defined before the current token
+        let mut definitions = self.definitions.iter().peekable();
+        let mut active = Vec::new(); // Names of the macros whose expansion is in progress
+        for token in tokens {
+            while let Some((_, name, definition)) = definitions.next_if(|(line, _, _)| *line < token.line) {
+                macros.insert(name.as_str(), definition);
+            }
+            expand_token(&macros, token, &mut active, &mut out);
+        }
+        out
+    }
+}
+
+// Append a token to the output, or the expansion of the macro it names
+fn expand_token(macros: &HashMap<&str, &Macro>, token: Spanned<Token>, active: &mut Vec<String>, out: &mut Vec<Spanned<Token>>) {
+    let definition = match &token.value {
+        Token::Identifier(name) if !active.contains(name) => macros.get(name.as_str()).map(|definition| (name, definition)),
+        _ => None,
+    };
+    let Some((name, definition)) = definition else {
+        out.push(token); // Not a macro, or one inside its own expansion
+        return;
+    };
+    active.push(name.clone());
+    for value in &definition.tokens {
+        expand_token(macros, Spanned { value: value.clone(), line: token.line, col: token.col }, active, out);
+    }
+    active.pop();
+}
+
+// Replace every trigraph with the character it stands for, in one pass from left to right.
+// No replaced character is scanned again, so the '\\' from '??/' only ever escapes the
+// character after the trigraph. Columns after a trigraph are counted in the replaced text.
+pub fn replace_trigraphs(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut rest = source;
+    while let Some(i) = rest.find("??") {
+        let (before, after) = rest.split_at(i);
+        out.push_str(before);
+        let third = after[2..].chars().next();
+        match TRIGRAPHS.iter().find(|(c, _)| Some(*c) == third) {
+            Some((_, replacement)) => {
+                out.push(*replacement);
+                rest = &after[3..];
+            }
+            None => {
+                // Only the first '?' is kept, so '???=' still ends in the trigraph '??='
+                out.push('?');
+                rest = &after[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// Split a source into lines ending in '\n', '\r\n', or a lone '\r', as the lexer counts them.
+// Like `str::lines`, a line break at the very end starts no extra empty line.
+fn split_lines(source: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(source).filter(|source| !source.is_empty());
+    std::iter::from_fn(move || {
+        let text = rest?;
+        let Some(end) = text.find(['\r', '\n']) else {
+            rest = None;
+            return Some(text);
+        };
+        let next = if text[end..].starts_with("\r\n") { end + 2 } else { end + 1 };
+        rest = Some(&text[next..]).filter(|rest| !rest.is_empty());
+        Some(&text[..end])
+    })
+}
+
+// Split a leading identifier off some text, returning it and the rest
+fn split_identifier(text: &str) -> (&str, &str) {
+    let end = text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(text.len());
+    text.split_at(end)
+}
+
+// Whether some text is a whole identifier, the only thing a macro can be named
+fn is_identifier(text: &str) -> bool {
+    let (name, rest) = split_identifier(text);
+    !name.is_empty() && rest.is_empty() && !name.starts_with(|c: char| c.is_ascii_digit())
+}
+
+// Whether a line ends inside a '/* */' comment, given whether it starts inside one. Quoted
+// text is skipped, so a '/*' inside a string or character literal opens nothing.
+fn ends_in_comment(line: &str, mut in_comment: bool) -> bool {
+    let mut chars = line.chars().peekable();
+    let mut quote = None; // The quote character of the literal being skipped, if any
+    while let Some(c) = chars.next() {
+        if in_comment {
+            if c == '*' && chars.peek() == Some(&'/') {
+                chars.next();
+                in_comment = false;
+            }
+        } else if let Some(q) = quote {
+            if c == '\\' {
+                chars.next(); // An escaped character never ends the literal
+            } else if c == q {
+                quote = None;
+            }
+        } else if c == '"' || c == '\'' {
+            quote = Some(c);
+        } else if c == '/' && chars.peek() == Some(&'/') {
+            break; // The rest of the line is a comment
+        } else if c == '/' && chars.peek() == Some(&'*') {
+            chars.next();
+            in_comment = true;
+        }
+    }
+    in_comment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Preprocess a source that has no '#include', then lex it and expand its macros
+    fn preprocess(preprocessor: &mut Preprocessor, source: &str) -> Result<Vec<Token>, String> {
+        let source = preprocessor.process(source, Path::new("test.c"))?;
+        let tokens = tokenize(&source).unwrap();
+        Ok(preprocessor.expand(tokens).into_iter().map(|token| token.value).collect())
+    }
+
+    // Lex a source without preprocessing it, for comparison
+    fn lex(source: &str) -> Vec<Token> {
+        tokenize(source).unwrap().into_iter().map(|token| token.value).collect()
+    }
+
+    // Write the given files into a directory of their own for one test
+    fn write_files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("compiler-preprocess-{}-{}", std::process::id(), test));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, contents) in files {
+            fs::write(dir.join(name), contents).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn object_macro_is_replaced_by_its_value() {
+        let tokens = preprocess(&mut Preprocessor::new(), "#define MAX 100\nint x = MAX;").unwrap();
+        assert_eq!(tokens, lex("int x = 100;"));
+    }
+
+    #[test]
+    fn define_line_produces_no_tokens() {
+        assert!(preprocess(&mut Preprocessor::new(), "#define MAX 100\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn every_line_ending_ends_a_directive() {
+        for source in ["#define N 1\nreturn N;", "#define N 1\r\nreturn N;", "#define N 1\rreturn N;"] {
+            assert_eq!(preprocess(&mut Preprocessor::new(), source).unwrap(), lex("return 1;"), "{:?}", source);
+        }
+        let source = Preprocessor::new().process("a\rb\r\n\nc\r", Path::new("test.c")).unwrap();
+        assert_eq!(source, "a\nb\n\nc\n");
+    }
+
+    #[test]
+    fn redefining_a_macro_warns() {
+        let mut preprocessor = Preprocessor::new();
+        preprocess(&mut preprocessor, "#define N 1\n#define N 1\n#define N 2\n").unwrap();
+        assert_eq!(preprocessor.warnings(), ["warning: macro 'N' redefined at line 3"]);
+    }
+
+    #[test]
+    fn name_used_before_its_define_is_left_alone() {
+        let tokens = preprocess(&mut Preprocessor::new(), "return N;\n#define N 1\nreturn N;").unwrap();
+        assert_eq!(tokens, lex("return N; return 1;"));
+    }
+
+    #[test]
+    fn redefinition_applies_from_its_line_on() {
+        let tokens = preprocess(&mut Preprocessor::new(), "#define N 1\nN\n#define N 2\nN\n").unwrap();
+        assert_eq!(tokens, lex("1 2"));
+    }
+
+    #[test]
+    fn local_include_is_spliced_in_place() {
+        let dir = write_files("include", &[("defs.h", "int helper(void) { return 1; }\n"), ("main.c", "#include \"defs.h\"\nint main(void) { return helper(); }\n")]);
+        let main = dir.join("main.c");
+        let source = Preprocessor::new().process(&fs::read_to_string(&main).unwrap(), &main).unwrap();
+        assert_eq!(lex(&source), lex("int helper(void) { return 1; } int main(void) { return helper(); }"));
+    }
+
+    #[test]
+    fn included_definitions_apply_after_their_lines() {
+        let dir = write_files("include_define", &[("defs.h", "A\n#define A 1\n\n"), ("main.c", "#include \"defs.h\"\nA B\n#define B 2\nA B\n")]);
+        let main = dir.join("main.c");
+        let mut preprocessor = Preprocessor::new();
+        let source = preprocessor.process(&fs::read_to_string(&main).unwrap(), &main).unwrap();
+        let tokens: Vec<Token> = preprocessor.expand(tokenize(&source).unwrap()).into_iter().map(|token| token.value).collect();
+        assert_eq!(tokens, lex("A 1 B 1 2"));
+    }
+
+    #[test]
+    fn self_include_is_a_cycle() {
+        let dir = write_files("include_cycle", &[("loop.c", "#include \"loop.c\"\n")]);
+        let path = dir.join("loop.c");
+        let error = Preprocessor::new().process(&fs::read_to_string(&path).unwrap(), &path).unwrap_err();
+        assert!(error.contains("'#include \"loop.c\"' at line 1 forms an include cycle"), "{}", error);
+    }
+
+    #[test]
+    fn ifdef_keeps_the_branch_for_a_defined_macro() {
+        let source = "#define DEBUG\n#ifdef DEBUG\nint a;\n#else\nint b;\n#endif\n#ifndef DEBUG\nint c;\n#endif\n";
+        assert_eq!(preprocess(&mut Preprocessor::new(), source).unwrap(), lex("int a;"));
+    }
+
+    #[test]
+    fn ifdef_skips_the_branch_for_an_undefined_macro() {
+        let source = "#ifdef DEBUG\nint a;\n#ifdef ALSO\nint b;\n#endif\n#else\nint c;\n#endif\n";
+        assert_eq!(preprocess(&mut Preprocessor::new(), source).unwrap(), lex("int c;"));
+    }
+
+    #[test]
+    fn unmatched_conditionals_are_errors() {
+        assert_eq!(
+            preprocess(&mut Preprocessor::new(), "int a;\n#endif\n").unwrap_err(),
+            "Preprocessor Error: '#endif' without '#ifdef' or '#ifndef' at line 2"
+        );
+        assert_eq!(
+            preprocess(&mut Preprocessor::new(), "#ifdef A\nint a;\n").unwrap_err(),
+            "Preprocessor Error: Missing '#endif' for the conditional at line 1"
+        );
+    }
+
+    #[test]
+    fn command_line_definition_enables_ifdef_and_substitutes() {
+        let mut preprocessor = Preprocessor::new();
+        preprocessor.define("DEBUG", "1").unwrap();
+        let tokens = preprocess(&mut preprocessor, "#ifdef DEBUG\nint x = DEBUG;\n#endif\n").unwrap();
+        assert_eq!(tokens, lex("int x = 1;"));
+    }
+
+    #[test]
+    fn trigraphs_are_replaced_only_when_enabled() {
+        let mut preprocessor = Preprocessor::new();
+        preprocessor.set_trigraphs(true);
+        assert_eq!(preprocess(&mut preprocessor, "??<??>").unwrap(), vec![Token::OpenBrace, Token::CloseBrace]);
+        assert_eq!(replace_trigraphs(r#""??/n""#), r#""\n""#);
+        let tokens = preprocess(&mut Preprocessor::new(), "??<").unwrap();
+        assert_eq!(tokens, vec![Token::Question, Token::Question, Token::Less]);
+    }
+}
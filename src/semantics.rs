@@ -0,0 +1,351 @@
+// Semantic analysis: checks the parsed program for errors the grammar can't express,
+// such as declaring the same variable twice in one scope or using one never declared,
+// and renames every variable to a unique name so later passes can ignore scoping
+use crate::ast::{BlockItem, Declaration, Expr, ForInit, Function, Program, Statement}; // Import the AST node types
+use std::collections::HashMap; // Import map type for the names declared in a scope
+
+// Check the program's variable declarations and uses, running after parsing and before codegen.
+// Returns a copy of the program where each declared variable has a unique name like 'x.1'.
+pub fn resolve(program: &Program) -> Result<Program, String> {
+    // The storage classes are recorded, but nothing gives a file-scope variable storage yet
+    if let Some(declaration) = program.variables.first() {
+        return Err(format!("Semantic Error: file-scope variable '{}' is not supported yet", declaration.name));
+    }
+    // The counter is shared so unique names never repeat, even across functions
+    let mut resolver = Resolver { scopes: Vec::new(), counter: 0 };
+    let functions = program
+        .functions
+        .iter()
+        .map(|function| resolver.resolve_function(function))
+        .collect::<Result<_, _>>()?;
+    Ok(Program { functions, variables: Vec::new() })
+}
+
+// State for walking a function body
+struct Resolver {
+    scopes: Vec<HashMap<String, String>>, // Source name to unique name in each enclosing scope, innermost last
+    counter: usize, // Number of variables renamed so far, used to build unique names
+}
+
+impl Resolver {
+    // Resolve a function; its parameters and the top level of its body share the outermost scope
+    fn resolve_function(&mut self, function: &Function) -> Result<Function, String> {
+        self.scopes = vec![HashMap::new()];
+        let mut params = Vec::new();
+        for (ty, name) in &function.params {
+            let param = self.resolve_declaration(&Declaration { storage: None, name: name.clone(), init: None })?;
+            params.push((ty.clone(), param.name));
+        }
+        let body = self.resolve_block_items(&function.body)?;
+        Ok(Function {
+            storage: function.storage.clone(),
+            return_type: function.return_type.clone(),
+            name: function.name.clone(),
+            params,
+            unspecified_params: function.unspecified_params,
+            body,
+        })
+    }
+
+    // Resolve the items of a block in the current scope
+    fn resolve_block_items(&mut self, items: &[BlockItem]) -> Result<Vec<BlockItem>, String> {
+        items
+            .iter()
+            .map(|item| match item {
+                BlockItem::Declaration(declaration) => Ok(BlockItem::Declaration(self.resolve_declaration(declaration)?)),
+                BlockItem::Statement(statement) => Ok(BlockItem::Statement(self.resolve_statement(statement)?)),
+            })
+            .collect()
+    }
+
+    // Record a declaration in the innermost scope under a fresh unique name; shadowing an
+    // outer scope is allowed. The name is in scope in its own initializer, as in C.
+    fn resolve_declaration(&mut self, declaration: &Declaration) -> Result<Declaration, String> {
+        self.counter += 1;
+        let unique = format!("{}.{}", declaration.name, self.counter);
+        let scope = self.scopes.last_mut().unwrap(); // Inside a function there is always at least its own scope
+        if scope.contains_key(&declaration.name) {
+            return Err(format!("Semantic Error: duplicate declaration of variable '{}'", declaration.name));
+        }
+        scope.insert(declaration.name.clone(), unique.clone());
+        let init = self.resolve_optional_expr(&declaration.init)?;
+        Ok(Declaration { storage: declaration.storage.clone(), name: unique, init })
+    }
+
+    // Resolve the declarations and variable uses nested inside a statement
+    fn resolve_statement(&mut self, statement: &Statement) -> Result<Statement, String> {
+        let resolved = match statement {
+            Statement::Return(expr) => Statement::Return(self.resolve_optional_expr(expr)?),
+            Statement::Expression(expr) => Statement::Expression(self.resolve_expr(expr)?),
+            Statement::If { cond, then, else_branch } => Statement::If {
+                cond: self.resolve_expr(cond)?,
+                then: Box::new(self.resolve_statement(then)?),
+                else_branch: match else_branch {
+                    Some(else_branch) => Some(Box::new(self.resolve_statement(else_branch)?)),
+                    None => None,
+                },
+            },
+            Statement::While { cond, body } => Statement::While {
+                cond: self.resolve_expr(cond)?,
+                body: Box::new(self.resolve_statement(body)?),
+            },
+            Statement::DoWhile { body, cond } => Statement::DoWhile {
+                body: Box::new(self.resolve_statement(body)?),
+                cond: self.resolve_expr(cond)?,
+            },
+            Statement::For { init, cond, post, body } => {
+                // The loop header opens a scope of its own around the body
+                self.scopes.push(HashMap::new());
+                let result = self.resolve_for(init, cond, post, body);
+                self.scopes.pop();
+                result?
+            }
+            Statement::Block(items) => {
+                self.scopes.push(HashMap::new());
+                let result = self.resolve_block_items(items);
+                self.scopes.pop();
+                Statement::Block(result?)
+            }
+            Statement::Goto(label) => Statement::Goto(label.clone()), // Labels are checked separately from variables
+            Statement::Label(label, statement) => Statement::Label(label.clone(), Box::new(self.resolve_statement(statement)?)),
+            Statement::Switch { value, body } => Statement::Switch {
+                value: self.resolve_expr(value)?,
+                body: Box::new(self.resolve_statement(body)?),
+            },
+            Statement::Case(value, statement) => Statement::Case(self.resolve_expr(value)?, Box::new(self.resolve_statement(statement)?)),
+            Statement::Default(statement) => Statement::Default(Box::new(self.resolve_statement(statement)?)),
+            Statement::Break => Statement::Break,
+            Statement::Continue => Statement::Continue,
+        };
+        Ok(resolved)
+    }
+
+    // Resolve the parts of a for loop inside the scope its header opened
+    fn resolve_for(&mut self, init: &Option<ForInit>, cond: &Option<Expr>, post: &Option<Expr>, body: &Statement) -> Result<Statement, String> {
+        let init = match init {
+            Some(ForInit::Declaration(declaration)) => Some(ForInit::Declaration(self.resolve_declaration(declaration)?)),
+            Some(ForInit::Expression(expr)) => Some(ForInit::Expression(self.resolve_expr(expr)?)),
+            None => None,
+        };
+        let cond = self.resolve_optional_expr(cond)?;
+        let post = self.resolve_optional_expr(post)?;
+        let body = Box::new(self.resolve_statement(body)?);
+        Ok(Statement::For { init, cond, post, body })
+    }
+
+    // Resolve an expression that may be absent, like an empty for loop clause
+    fn resolve_optional_expr(&self, expr: &Option<Expr>) -> Result<Option<Expr>, String> {
+        match expr {
+            Some(expr) => Ok(Some(self.resolve_expr(expr)?)),
+            None => Ok(None),
+        }
+    }
+
+    // Replace every variable an expression reads or assigns with the unique name of the
+    // innermost declaration in scope, failing if there is none
+    fn resolve_expr(&self, expr: &Expr) -> Result<Expr, String> {
+        let resolved = match expr {
+            Expr::Constant(value) => Expr::Constant(*value),
+            Expr::FloatConstant(value) => Expr::FloatConstant(*value),
+            Expr::Var(name) => match self.scopes.iter().rev().find_map(|scope| scope.get(name)) {
+                Some(unique) => Expr::Var(unique.clone()),
+                None => return Err(format!("Semantic Error: undeclared variable '{}'", name)),
+            },
+            Expr::Unary(op, operand) => Expr::Unary(op.clone(), Box::new(self.resolve_expr(operand)?)),
+            Expr::Binary(op, left, right) => {
+                Expr::Binary(op.clone(), Box::new(self.resolve_expr(left)?), Box::new(self.resolve_expr(right)?))
+            }
+            Expr::Assign(left, right) => Expr::Assign(Box::new(self.resolve_expr(left)?), Box::new(self.resolve_expr(right)?)),
+            Expr::Conditional { cond, then, else_ } => Expr::Conditional {
+                cond: Box::new(self.resolve_expr(cond)?),
+                then: Box::new(self.resolve_expr(then)?),
+                else_: Box::new(self.resolve_expr(else_)?),
+            },
+            Expr::Subscript { base, index } => Expr::Subscript {
+                base: Box::new(self.resolve_expr(base)?),
+                index: Box::new(self.resolve_expr(index)?),
+            },
+            Expr::Deref(operand) => Expr::Deref(Box::new(self.resolve_expr(operand)?)),
+            Expr::SizeOf(operand) => Expr::SizeOf(Box::new(self.resolve_expr(operand)?)),
+            Expr::SizeOfType(ty) => Expr::SizeOfType(ty.clone()),
+            Expr::Cast(ty, operand) => Expr::Cast(ty.clone(), Box::new(self.resolve_expr(operand)?)),
+            Expr::Comma(left, right) => Expr::Comma(Box::new(self.resolve_expr(left)?), Box::new(self.resolve_expr(right)?)),
+            Expr::PrefixInc(operand) => Expr::PrefixInc(Box::new(self.resolve_expr(operand)?)),
+            Expr::PrefixDec(operand) => Expr::PrefixDec(Box::new(self.resolve_expr(operand)?)),
+            Expr::PostfixInc(operand) => Expr::PostfixInc(Box::new(self.resolve_expr(operand)?)),
+            Expr::PostfixDec(operand) => Expr::PostfixDec(Box::new(self.resolve_expr(operand)?)),
+            Expr::AddrOf(operand) => Expr::AddrOf(Box::new(self.resolve_expr(operand)?)),
+            // Field names belong to the struct type, so only the base is resolved
+            Expr::Member { base, field } => Expr::Member { base: Box::new(self.resolve_expr(base)?), field: field.clone() },
+            Expr::PtrMember { base, field } => Expr::PtrMember { base: Box::new(self.resolve_expr(base)?), field: field.clone() },
+            // Function names live apart from variables, so only the arguments are resolved
+            Expr::Call { name, args } => Expr::Call {
+                name: name.clone(),
+                args: args.iter().map(|arg| self.resolve_expr(arg)).collect::<Result<_, _>>()?,
+            },
+        };
+        Ok(resolved)
+    }
+}
+
+// A declared name as it appears in the symbol table dump
+struct Symbol {
+    name: String, // Name as written in the source
+    kind: &'static str, // 'function', 'parameter', or 'variable'
+    ty: String, // Type, with the signature for functions
+    depth: usize, // Scope nesting depth: 0 for functions, 1 for a function's outermost scope
+    unique: String, // Name given by resolution
+}
+
+// Render every function and variable declared in a resolved program as a table, in
+// declaration order. Variables are recovered from their unique 'name.N' names.
+pub fn dump_symbols(program: &Program) -> String {
+    let mut symbols = Vec::new();
+    for function in &program.functions {
+        let params: Vec<String> = function.params.iter().map(|(ty, _)| ty.to_string()).collect();
+        let params = if params.is_empty() && !function.unspecified_params { "void".to_string() } else { params.join(", ") };
+        symbols.push(Symbol {
+            name: function.name.clone(),
+            kind: "function",
+            ty: format!("{}({})", function.return_type, params),
+            depth: 0,
+            unique: function.name.clone(),
+        });
+        for (ty, unique) in &function.params {
+            symbols.push(variable_symbol(unique, "parameter", &ty.to_string(), 1));
+        }
+        collect_block_symbols(&function.body, 1, &mut symbols);
+    }
+
+    let header = Symbol { name: "name".into(), kind: "kind", ty: "type".into(), depth: 0, unique: "unique".into() };
+    let name_width = symbols.iter().chain([&header]).map(|symbol| symbol.name.len()).max().unwrap_or(0);
+    let type_width = symbols.iter().chain([&header]).map(|symbol| symbol.ty.len()).max().unwrap_or(0);
+    let mut out = format!("{:<name_width$}  {:<9}  {:<type_width$}  depth  unique\n", "name", "kind", "type");
+    for symbol in &symbols {
+        out.push_str(&format!(
+            "{:<name_width$}  {:<9}  {:<type_width$}  {:<5}  {}\n",
+            symbol.name, symbol.kind, symbol.ty, symbol.depth, symbol.unique
+        ));
+    }
+    out
+}
+
+// Build the entry for a variable from its unique name, whose source name is everything before the last '.'
+fn variable_symbol(unique: &str, kind: &'static str, ty: &str, depth: usize) -> Symbol {
+    let name = unique.rsplit_once('.').map_or(unique, |(name, _)| name);
+    Symbol { name: name.to_string(), kind, ty: ty.to_string(), depth, unique: unique.to_string() }
+}
+
+// Add the variables declared in a block, which opens no scope of its own for a function body
+fn collect_block_symbols(items: &[BlockItem], depth: usize, symbols: &mut Vec<Symbol>) {
+    for item in items {
+        match item {
+            BlockItem::Declaration(declaration) => symbols.push(variable_symbol(&declaration.name, "variable", "int", depth)),
+            BlockItem::Statement(statement) => collect_statement_symbols(statement, depth, symbols),
+        }
+    }
+}
+
+// Add the variables declared anywhere inside a statement, at the scope depth resolution gave them
+fn collect_statement_symbols(statement: &Statement, depth: usize, symbols: &mut Vec<Symbol>) {
+    match statement {
+        Statement::Block(items) => collect_block_symbols(items, depth + 1, symbols),
+        Statement::For { init, body, .. } => {
+            // The loop header opens a scope around the body
+            if let Some(ForInit::Declaration(declaration)) = init {
+                symbols.push(variable_symbol(&declaration.name, "variable", "int", depth + 1));
+            }
+            collect_statement_symbols(body, depth + 1, symbols);
+        }
+        Statement::If { then, else_branch, .. } => {
+            collect_statement_symbols(then, depth, symbols);
+            if let Some(else_branch) = else_branch {
+                collect_statement_symbols(else_branch, depth, symbols);
+            }
+        }
+        Statement::While { body, .. }
+        | Statement::DoWhile { body, .. }
+        | Statement::Switch { body, .. }
+        | Statement::Case(_, body)
+        | Statement::Default(body)
+        | Statement::Label(_, body) => collect_statement_symbols(body, depth, symbols),
+        Statement::Return(_) | Statement::Expression(_) | Statement::Goto(_) | Statement::Break | Statement::Continue => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    // Lex, parse, and resolve a source
+    fn resolve_source(source: &str) -> Result<Program, String> {
+        resolve(&parse(tokenize(source).unwrap()).unwrap())
+    }
+
+    // Resolve a 'main' with the given body
+    fn resolve_body(body: &str) -> Result<Program, String> {
+        resolve_source(&format!("int main(void) {{ {} }}", body))
+    }
+
+    #[test]
+    fn inner_scope_may_shadow_an_outer_one() {
+        assert!(resolve_body("int x = 1; { int x = 2; } return x;").is_ok());
+    }
+
+    #[test]
+    fn redeclaring_in_the_same_scope_is_an_error() {
+        assert_eq!(
+            resolve_body("int x = 1; int x = 2; return x;").unwrap_err(),
+            "Semantic Error: duplicate declaration of variable 'x'"
+        );
+    }
+
+    #[test]
+    fn using_an_undeclared_variable_is_an_error() {
+        assert_eq!(resolve_body("return y;").unwrap_err(), "Semantic Error: undeclared variable 'y'");
+        assert_eq!(resolve_body("y = 1; return 0;").unwrap_err(), "Semantic Error: undeclared variable 'y'");
+        assert!(resolve_body("int y; y = 1; return y;").is_ok());
+    }
+
+    #[test]
+    fn file_scope_variables_are_rejected_clearly() {
+        let error = resolve_source("static int x; int main(void) { return 0; }").unwrap_err();
+        assert_eq!(error, "Semantic Error: file-scope variable 'x' is not supported yet");
+    }
+
+    #[test]
+    fn a_block_variable_is_gone_after_the_block() {
+        assert_eq!(resolve_body("{ int y = 1; } return y;").unwrap_err(), "Semantic Error: undeclared variable 'y'");
+    }
+
+    #[test]
+    fn shadowed_variables_resolve_to_the_innermost_declaration() {
+        let program = resolve_body("int x = 1; { int x = 2; x = 3; } return x;").unwrap();
+        let body = &program.functions[0].body;
+        let BlockItem::Declaration(outer) = &body[0] else { panic!("expected a declaration") };
+        let BlockItem::Statement(Statement::Block(inner)) = &body[1] else { panic!("expected a block") };
+        let BlockItem::Declaration(shadow) = &inner[0] else { panic!("expected a declaration") };
+        let BlockItem::Statement(Statement::Expression(Expr::Assign(target, _))) = &inner[1] else { panic!("expected an assignment") };
+        let BlockItem::Statement(Statement::Return(Some(Expr::Var(returned)))) = &body[2] else { panic!("expected a return") };
+        assert_ne!(outer.name, shadow.name);
+        assert!(matches!(&**target, Expr::Var(name) if *name == shadow.name));
+        assert_eq!(*returned, outer.name);
+    }
+
+    #[test]
+    fn symbol_dump_lists_the_function_and_its_locals() {
+        let program = resolve_body("int a = 1; int b = 2; return a + b;").unwrap();
+        let dump = dump_symbols(&program);
+        let rows: Vec<Vec<&str>> = dump.lines().skip(1).map(|line| line.split_whitespace().collect()).collect();
+        assert_eq!(
+            rows,
+            vec![
+                vec!["main", "function", "int(void)", "0", "main"],
+                vec!["a", "variable", "int", "1", "a.1"],
+                vec!["b", "variable", "int", "1", "b.2"],
+            ]
+        );
+    }
+}
@@ -0,0 +1,406 @@
+// TACKY: a three-address intermediate representation between the AST and the
+// assembly backend, where every subexpression's result is named by a temporary
+use crate::ast::{BinaryOp, BlockItem, Declaration, Expr, ForInit, Function, Program, Statement, Type, UnaryOp}; // Import the AST node types
+use crate::typecheck::value_type; // Import the type of a checked expression, for 'sizeof'
+use std::fmt; // Import formatting traits for printing the IR
+
+/// Represents a whole program in TACKY form.
+#[derive(Debug, Clone)]
+pub struct TackyProgram {
+    pub functions: Vec<TackyFunction>, // The program's functions in source order
+}
+
+/// Represents a function as a flat list of TACKY instructions.
+#[derive(Debug, Clone)]
+pub struct TackyFunction {
+    pub name: String,                  // Name of the function
+    pub params: Vec<String>,           // Names of the parameters, in order
+    pub body: Vec<TackyInstruction>,   // Instructions in execution order
+}
+
+/// Represents a single three-address instruction.
+#[derive(Debug, Clone)]
+pub enum TackyInstruction {
+    Return(Option<Val>),             // Return from the function, with a value unless it is 'void'
+    Unary(UnaryOp, Val, Val),        // 'dst = op src'
+    Binary(BinaryOp, Val, Val, Val), // 'dst = left op right'
+    Copy(Val, Val),                  // 'dst = src'
+    Jump(String),                    // Unconditional jump to a label
+    JumpIfZero(Val, String),         // Jump to a label when the value is 0
+    JumpIfNotZero(Val, String),      // Jump to a label when the value is not 0
+    Label(String),                   // Jump target
+}
+
+/// Represents an instruction operand.
+#[derive(Debug, Clone)]
+pub enum Val {
+    Constant(i32), // Integer constant like '5'
+    Var(String),   // Named variable or compiler-generated temporary
+}
+
+// Lower a whole program to TACKY
+pub fn emit_tacky(program: &Program) -> Result<TackyProgram, String> {
+    let functions = program.functions.iter().map(emit_function).collect::<Result<_, _>>()?;
+    Ok(TackyProgram { functions })
+}
+
+// Lower one function, ending with an implicit return for bodies that fall off the end:
+// 'return 0' from an 'int' function and a bare 'return' from a 'void' one
+fn emit_function(function: &Function) -> Result<TackyFunction, String> {
+    let mut gen = TackyGen { body: Vec::new(), counter: 0, loops: Vec::new() };
+    for item in &function.body {
+        gen.emit_block_item(item)?;
+    }
+    let value = if function.return_type == Type::Void { None } else { Some(Val::Constant(0)) };
+    gen.body.push(TackyInstruction::Return(value));
+    let params = function.params.iter().map(|(_, name)| name.clone()).collect();
+    Ok(TackyFunction { name: function.name.clone(), params, body: gen.body })
+}
+
+// State for lowering the body of a single function
+struct TackyGen {
+    body: Vec<TackyInstruction>,   // Instructions emitted so far
+    counter: usize,                // Next number for temporaries and labels
+    loops: Vec<(String, String)>,  // Continue and break labels of the enclosing loops, innermost last
+}
+
+impl TackyGen {
+    // Make a fresh temporary variable
+    fn make_temp(&mut self) -> Val {
+        let name = format!("tmp.{}", self.counter);
+        self.counter += 1;
+        Val::Var(name)
+    }
+
+    // Make a fresh label with a readable prefix
+    fn make_label(&mut self, prefix: &str) -> String {
+        let label = format!("{}.{}", prefix, self.counter);
+        self.counter += 1;
+        label
+    }
+
+    // Lower a declaration or statement
+    fn emit_block_item(&mut self, item: &BlockItem) -> Result<(), String> {
+        match item {
+            BlockItem::Declaration(declaration) => self.emit_declaration(declaration),
+            BlockItem::Statement(statement) => self.emit_statement(statement),
+        }
+    }
+
+    // Lower a declaration; only an initializer produces code
+    fn emit_declaration(&mut self, declaration: &Declaration) -> Result<(), String> {
+        if let Some(init) = &declaration.init {
+            let value = self.emit_expr(init)?;
+            self.body.push(TackyInstruction::Copy(value, Val::Var(declaration.name.clone())));
+        }
+        Ok(())
+    }
+
+    // Lower a statement
+    fn emit_statement(&mut self, statement: &Statement) -> Result<(), String> {
+        match statement {
+            Statement::Return(expr) => {
+                let value = match expr {
+                    Some(expr) => Some(self.emit_expr(expr)?),
+                    None => None,
+                };
+                self.body.push(TackyInstruction::Return(value));
+            }
+            Statement::Expression(expr) => {
+                self.emit_expr(expr)?; // Evaluate for side effects only
+            }
+            Statement::If { cond, then, else_branch } => {
+                let end_label = self.make_label("if_end");
+                let cond = self.emit_expr(cond)?;
+                match else_branch {
+                    Some(else_branch) => {
+                        let else_label = self.make_label("if_else");
+                        self.body.push(TackyInstruction::JumpIfZero(cond, else_label.clone()));
+                        self.emit_statement(then)?;
+                        self.body.push(TackyInstruction::Jump(end_label.clone()));
+                        self.body.push(TackyInstruction::Label(else_label));
+                        self.emit_statement(else_branch)?;
+                    }
+                    None => {
+                        self.body.push(TackyInstruction::JumpIfZero(cond, end_label.clone()));
+                        self.emit_statement(then)?;
+                    }
+                }
+                self.body.push(TackyInstruction::Label(end_label));
+            }
+            Statement::While { cond, body } => {
+                let continue_label = self.make_label("continue");
+                let break_label = self.make_label("break");
+                self.body.push(TackyInstruction::Label(continue_label.clone()));
+                let cond = self.emit_expr(cond)?;
+                self.body.push(TackyInstruction::JumpIfZero(cond, break_label.clone()));
+                self.emit_loop_body(body, &continue_label, &break_label)?;
+                self.body.push(TackyInstruction::Jump(continue_label));
+                self.body.push(TackyInstruction::Label(break_label));
+            }
+            Statement::DoWhile { body, cond } => {
+                // The body runs once before the first test; 'continue' jumps to the test
+                let start_label = self.make_label("do_start");
+                let continue_label = self.make_label("continue");
+                let break_label = self.make_label("break");
+                self.body.push(TackyInstruction::Label(start_label.clone()));
+                self.emit_loop_body(body, &continue_label, &break_label)?;
+                self.body.push(TackyInstruction::Label(continue_label));
+                let cond = self.emit_expr(cond)?;
+                self.body.push(TackyInstruction::JumpIfNotZero(cond, start_label));
+                self.body.push(TackyInstruction::Label(break_label));
+            }
+            Statement::For { init, cond, post, body } => {
+                match init {
+                    Some(ForInit::Declaration(declaration)) => self.emit_declaration(declaration)?,
+                    Some(ForInit::Expression(expr)) => {
+                        self.emit_expr(expr)?;
+                    }
+                    None => {}
+                }
+                let start_label = self.make_label("for_start");
+                let continue_label = self.make_label("continue");
+                let break_label = self.make_label("break");
+                self.body.push(TackyInstruction::Label(start_label.clone()));
+                // A missing condition loops forever
+                if let Some(cond) = cond {
+                    let cond = self.emit_expr(cond)?;
+                    self.body.push(TackyInstruction::JumpIfZero(cond, break_label.clone()));
+                }
+                self.emit_loop_body(body, &continue_label, &break_label)?;
+                self.body.push(TackyInstruction::Label(continue_label));
+                if let Some(post) = post {
+                    self.emit_expr(post)?;
+                }
+                self.body.push(TackyInstruction::Jump(start_label));
+                self.body.push(TackyInstruction::Label(break_label));
+            }
+            // User labels can't collide with generated ones, which always contain a '.'
+            Statement::Goto(label) => self.body.push(TackyInstruction::Jump(label.clone())),
+            Statement::Label(label, statement) => {
+                self.body.push(TackyInstruction::Label(label.clone()));
+                self.emit_statement(statement)?;
+            }
+            Statement::Switch { .. } | Statement::Case(..) | Statement::Default(_) => {
+                return Err("Tacky Error: 'switch' statements are not supported yet".to_string())
+            }
+            Statement::Break => match self.loops.last() {
+                Some((_, break_label)) => self.body.push(TackyInstruction::Jump(break_label.clone())),
+                None => return Err("Tacky Error: 'break' statement not in a loop".to_string()),
+            },
+            Statement::Continue => match self.loops.last() {
+                Some((continue_label, _)) => self.body.push(TackyInstruction::Jump(continue_label.clone())),
+                None => return Err("Tacky Error: 'continue' statement not in a loop".to_string()),
+            },
+            Statement::Block(items) => {
+                for item in items {
+                    self.emit_block_item(item)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Lower a loop body with 'break' and 'continue' bound to the given labels
+    fn emit_loop_body(&mut self, body: &Statement, continue_label: &str, break_label: &str) -> Result<(), String> {
+        self.loops.push((continue_label.to_string(), break_label.to_string()));
+        let result = self.emit_statement(body);
+        self.loops.pop();
+        result
+    }
+
+    // Lower '++' or '--': add or subtract one in place, yielding the old value for the
+    // postfix forms and the new one for the prefix forms
+    fn emit_step(&mut self, operand: &Expr, op: BinaryOp, postfix: bool) -> Result<Val, String> {
+        let Expr::Var(name) = operand else {
+            return Err("Tacky Error: invalid operand of '++' or '--'".to_string());
+        };
+        let var = Val::Var(name.clone());
+        let result = if postfix {
+            let old = self.make_temp();
+            self.body.push(TackyInstruction::Copy(var.clone(), old.clone()));
+            old
+        } else {
+            var.clone()
+        };
+        self.body.push(TackyInstruction::Binary(op, var.clone(), Val::Constant(1), var));
+        Ok(result)
+    }
+
+    // Lower an expression, returning the value that holds its result
+    fn emit_expr(&mut self, expr: &Expr) -> Result<Val, String> {
+        match expr {
+            Expr::Constant(value) => Ok(Val::Constant(*value)),
+            Expr::Var(name) => Ok(Val::Var(name.clone())),
+            Expr::Unary(op, operand) => {
+                let src = self.emit_expr(operand)?;
+                let dst = self.make_temp();
+                self.body.push(TackyInstruction::Unary(op.clone(), src, dst.clone()));
+                Ok(dst)
+            }
+            Expr::Binary(BinaryOp::And, left, right) => self.emit_logical(left, right, true),
+            Expr::Binary(BinaryOp::Or, left, right) => self.emit_logical(left, right, false),
+            Expr::Binary(op, left, right) => {
+                let left = self.emit_expr(left)?;
+                let right = self.emit_expr(right)?;
+                let dst = self.make_temp();
+                self.body.push(TackyInstruction::Binary(op.clone(), left, right, dst.clone()));
+                Ok(dst)
+            }
+            Expr::Assign(target, value) => {
+                let Expr::Var(name) = target.as_ref() else {
+                    return Err("Tacky Error: invalid assignment target".to_string());
+                };
+                let value = self.emit_expr(value)?;
+                let dst = Val::Var(name.clone());
+                self.body.push(TackyInstruction::Copy(value, dst.clone()));
+                Ok(dst)
+            }
+            // The operand is never evaluated, and type checking has ruled out 'void'
+            Expr::SizeOf(operand) => Ok(Val::Constant(value_type(operand).size().unwrap())),
+            Expr::SizeOfType(ty) => match ty.size() {
+                Some(size) => Ok(Val::Constant(size)),
+                None => Err(format!("Tacky Error: type '{}' has no size", ty)),
+            },
+            Expr::FloatConstant(_) | Expr::Cast(Type::Double, _) => Err("Tacky Error: 'double' values are not supported yet".to_string()),
+            // Without 'double' values, a cast to 'int' converts nothing, and one to 'void' only discards
+            Expr::Cast(_, operand) => self.emit_expr(operand),
+            Expr::Comma(left, right) => {
+                self.emit_expr(left)?; // Evaluate for side effects only
+                self.emit_expr(right)
+            }
+            Expr::PrefixInc(operand) => self.emit_step(operand, BinaryOp::Add, false),
+            Expr::PrefixDec(operand) => self.emit_step(operand, BinaryOp::Subtract, false),
+            Expr::PostfixInc(operand) => self.emit_step(operand, BinaryOp::Add, true),
+            Expr::PostfixDec(operand) => self.emit_step(operand, BinaryOp::Subtract, true),
+            Expr::Conditional { cond, then, else_ } => {
+                let else_label = self.make_label("cond_else");
+                let end_label = self.make_label("cond_end");
+                let dst = self.make_temp();
+                let cond = self.emit_expr(cond)?;
+                self.body.push(TackyInstruction::JumpIfZero(cond, else_label.clone()));
+                let then = self.emit_expr(then)?;
+                self.body.push(TackyInstruction::Copy(then, dst.clone()));
+                self.body.push(TackyInstruction::Jump(end_label.clone()));
+                self.body.push(TackyInstruction::Label(else_label));
+                let else_ = self.emit_expr(else_)?;
+                self.body.push(TackyInstruction::Copy(else_, dst.clone()));
+                self.body.push(TackyInstruction::Label(end_label));
+                Ok(dst)
+            }
+            Expr::Call { name, .. } => Err(format!("Tacky Error: call to '{}' is not supported yet", name)),
+            Expr::Subscript { .. } => Err("Tacky Error: array subscripts are not supported yet".to_string()),
+            Expr::Deref(_) | Expr::AddrOf(_) => Err("Tacky Error: pointers are not supported yet".to_string()),
+            Expr::Member { .. } | Expr::PtrMember { .. } => Err("Tacky Error: struct members are not supported yet".to_string()),
+        }
+    }
+
+    // Lower '&&' (is_and) or '||' with short-circuit jumps, producing 1 or 0
+    fn emit_logical(&mut self, left: &Expr, right: &Expr, is_and: bool) -> Result<Val, String> {
+        let (prefix, short_result) = if is_and { ("and_false", 0) } else { ("or_true", 1) };
+        let short_label = self.make_label(prefix);
+        let end_label = self.make_label(if is_and { "and_end" } else { "or_end" });
+        for operand in [left, right] {
+            let value = self.emit_expr(operand)?;
+            // '&&' stops at the first zero operand, '||' at the first non-zero one
+            self.body.push(if is_and {
+                TackyInstruction::JumpIfZero(value, short_label.clone())
+            } else {
+                TackyInstruction::JumpIfNotZero(value, short_label.clone())
+            });
+        }
+        let dst = self.make_temp();
+        self.body.push(TackyInstruction::Copy(Val::Constant(1 - short_result), dst.clone()));
+        self.body.push(TackyInstruction::Jump(end_label.clone()));
+        self.body.push(TackyInstruction::Label(short_label));
+        self.body.push(TackyInstruction::Copy(Val::Constant(short_result), dst.clone()));
+        self.body.push(TackyInstruction::Label(end_label));
+        Ok(dst)
+    }
+}
+
+impl fmt::Display for Val {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Val::Constant(value) => write!(f, "{}", value),
+            Val::Var(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+impl fmt::Display for TackyInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TackyInstruction::Return(Some(value)) => write!(f, "    return {}", value),
+            TackyInstruction::Return(None) => write!(f, "    return"),
+            TackyInstruction::Unary(op, src, dst) => write!(f, "    {} = {}{}", dst, op.symbol(), src),
+            TackyInstruction::Binary(op, left, right, dst) => {
+                write!(f, "    {} = {} {} {}", dst, left, op.symbol(), right)
+            }
+            TackyInstruction::Copy(src, dst) => write!(f, "    {} = {}", dst, src),
+            TackyInstruction::Jump(label) => write!(f, "    jump {}", label),
+            TackyInstruction::JumpIfZero(value, label) => write!(f, "    jump_if_zero {}, {}", value, label),
+            TackyInstruction::JumpIfNotZero(value, label) => write!(f, "    jump_if_not_zero {}, {}", value, label),
+            TackyInstruction::Label(label) => write!(f, "  {}:", label),
+        }
+    }
+}
+
+impl fmt::Display for TackyProgram {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for function in &self.functions {
+            writeln!(f, "function {}({}):", function.name, function.params.join(", "))?;
+            for instruction in &function.body {
+                writeln!(f, "{}", instruction)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    // Lower a 'main' with the given body and return its instructions as printed by '--tacky'
+    fn tacky_lines(body: &str) -> Vec<String> {
+        let program = parse(tokenize(&format!("int main(void) {{ {} }}", body)).unwrap()).unwrap();
+        let tacky = emit_tacky(&program).unwrap();
+        tacky.functions[0].body.iter().map(|instruction| instruction.to_string().trim().to_string()).collect()
+    }
+
+    #[test]
+    fn nested_binary_expression_gets_a_temporary_per_operator() {
+        assert_eq!(tacky_lines("return 1 + 2 * 3;"), vec!["tmp.0 = 2 * 3", "tmp.1 = 1 + tmp.0", "return tmp.1", "return 0"]);
+    }
+
+    #[test]
+    fn unary_operators_chain_through_temporaries() {
+        assert_eq!(tacky_lines("return -~2;"), vec!["tmp.0 = ~2", "tmp.1 = -tmp.0", "return tmp.1", "return 0"]);
+    }
+
+    #[test]
+    fn logical_and_jumps_past_the_right_operand() {
+        assert_eq!(
+            tacky_lines("return 2 && 3;"),
+            vec![
+                "jump_if_zero 2, and_false.0",
+                "jump_if_zero 3, and_false.0",
+                "tmp.2 = 1",
+                "jump and_end.1",
+                "and_false.0:",
+                "tmp.2 = 0",
+                "and_end.1:",
+                "return tmp.2",
+                "return 0",
+            ]
+        );
+    }
+
+    #[test]
+    fn variables_keep_their_names() {
+        assert_eq!(tacky_lines("int x = 5; return x;"), vec!["x = 5", "return x", "return 0"]);
+    }
+}
@@ -0,0 +1,343 @@
+// Type checking: verifies every expression is well-typed under the 'int'/'double'/'void' type system.
+// Runs after name resolution, so each variable already has a unique name and is known to be an 'int'.
+// A 'double' only comes from a floating-point constant or a cast.
+use crate::ast::{BinaryOp, BlockItem, Declaration, Expr, ForInit, Function, Program, Statement, Type, UnaryOp}; // Import the AST node types
+use std::collections::HashMap; // Import map type for the signatures of the functions in the file
+
+// Check the types of every expression in the program, failing on the first ill-typed one
+pub fn typecheck(program: &Program) -> Result<(), String> {
+    // Collect every signature first, so a function may call one defined later in the file
+    let mut functions = HashMap::new();
+    for function in &program.functions {
+        functions.insert(function.name.clone(), function);
+    }
+    for function in &program.functions {
+        check_signature(function)?;
+        let checker = TypeChecker { functions: &functions, function };
+        function.body.iter().try_for_each(|item| checker.check_block_item(item))?;
+    }
+    Ok(())
+}
+
+// Variables are all 'int' so far, so a 'double' can't be passed in or returned
+fn check_signature(function: &Function) -> Result<(), String> {
+    if function.return_type == Type::Double {
+        return Err(format!("Type Error: function '{}' returns 'double', which is not supported yet", function.name));
+    }
+    match function.params.iter().find(|(ty, _)| *ty == Type::Double) {
+        Some((_, name)) => Err(format!(
+            "Type Error: parameter '{}' of function '{}' has type 'double', which is not supported yet",
+            name, function.name
+        )),
+        None => Ok(()),
+    }
+}
+
+// The type arithmetic on two scalar operands produces: 'double' if either one is
+fn common_type(left: Type, right: Type) -> Type {
+    if left == Type::Double || right == Type::Double {
+        Type::Double
+    } else {
+        Type::Int
+    }
+}
+
+// The type of a value-producing expression in a program that has passed type checking, for
+// the passes after it. Without variables, parameters, or results of type 'double', the
+// expression alone decides between 'int' and 'double'; a call to a 'void' function counts as an 'int'.
+pub fn value_type(expr: &Expr) -> Type {
+    match expr {
+        Expr::FloatConstant(_) => Type::Double,
+        Expr::Cast(ty, _) => ty.clone(),
+        Expr::Unary(UnaryOp::Negate, operand) => value_type(operand),
+        Expr::Binary(BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Multiply | BinaryOp::Divide, left, right)
+        | Expr::Conditional { then: left, else_: right, .. } => common_type(value_type(left), value_type(right)),
+        Expr::Comma(_, right) => value_type(right),
+        _ => Type::Int,
+    }
+}
+
+// State for checking one function body
+struct TypeChecker<'a> {
+    functions: &'a HashMap<String, &'a Function>, // Every function defined in the file, by name
+    function: &'a Function, // The function being checked, whose return type each 'return' must match
+}
+
+impl TypeChecker<'_> {
+    // Check a declaration or statement
+    fn check_block_item(&self, item: &BlockItem) -> Result<(), String> {
+        match item {
+            BlockItem::Declaration(declaration) => self.check_declaration(declaration),
+            BlockItem::Statement(statement) => self.check_statement(statement),
+        }
+    }
+
+    // A variable is initialized with an 'int', or a 'double' that is converted to one
+    fn check_declaration(&self, declaration: &Declaration) -> Result<(), String> {
+        match &declaration.init {
+            Some(init) => self.expect_scalar(init, &format!("initializer of variable '{}'", declaration.name)).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    // Check the expressions nested inside a statement
+    fn check_statement(&self, statement: &Statement) -> Result<(), String> {
+        match statement {
+            Statement::Return(expr) => self.check_return(expr),
+            // The value of an expression statement is thrown away, so it may be 'void'
+            Statement::Expression(expr) => self.type_of(expr).map(|_| ()),
+            Statement::If { cond, then, else_branch } => {
+                self.expect_scalar(cond, "condition of 'if'")?;
+                self.check_statement(then)?;
+                match else_branch {
+                    Some(else_branch) => self.check_statement(else_branch),
+                    None => Ok(()),
+                }
+            }
+            Statement::While { cond, body } => {
+                self.expect_scalar(cond, "condition of 'while'")?;
+                self.check_statement(body)
+            }
+            Statement::DoWhile { body, cond } => {
+                self.check_statement(body)?;
+                self.expect_scalar(cond, "condition of 'do'-'while'").map(|_| ())
+            }
+            Statement::For { init, cond, post, body } => {
+                match init {
+                    Some(ForInit::Declaration(declaration)) => self.check_declaration(declaration)?,
+                    Some(ForInit::Expression(expr)) => self.type_of(expr).map(|_| ())?,
+                    None => {}
+                }
+                if let Some(cond) = cond {
+                    self.expect_scalar(cond, "condition of 'for'")?;
+                }
+                if let Some(post) = post {
+                    self.type_of(post)?;
+                }
+                self.check_statement(body)
+            }
+            Statement::Switch { value, body } => {
+                self.expect_int(value, "value of 'switch'")?;
+                self.check_statement(body)
+            }
+            Statement::Case(value, statement) => {
+                self.expect_int(value, "value of 'case'")?;
+                self.check_statement(statement)
+            }
+            Statement::Default(statement) | Statement::Label(_, statement) => self.check_statement(statement),
+            Statement::Block(items) => items.iter().try_for_each(|item| self.check_block_item(item)),
+            Statement::Goto(_) | Statement::Break | Statement::Continue => Ok(()),
+        }
+    }
+
+    // An 'int' function must return an 'int' value, and a 'void' function must return none
+    fn check_return(&self, expr: &Option<Expr>) -> Result<(), String> {
+        match (&self.function.return_type, expr) {
+            (Type::Int, Some(expr)) => self.expect_scalar(expr, "returned value").map(|_| ()),
+            (Type::Void, None) => Ok(()),
+            (Type::Int, None) => {
+                Err(format!("Type Error: 'return' without a value in function '{}' returning 'int'", self.function.name))
+            }
+            (Type::Void, Some(_)) => {
+                Err(format!("Type Error: 'return' with a value in function '{}' returning 'void'", self.function.name))
+            }
+            (Type::Double, _) => unreachable!("signatures with 'double' are rejected before the body is checked"),
+        }
+    }
+
+    // Fail unless an expression has type 'int'; `context` says where the value is used
+    fn expect_int(&self, expr: &Expr, context: &str) -> Result<(), String> {
+        match self.type_of(expr)? {
+            Type::Int => Ok(()),
+            ty => Err(format!("Type Error: {} has type '{}', but an 'int' is required", context, ty)),
+        }
+    }
+
+    // Fail unless an expression has a type that can be tested and converted, 'int' or 'double',
+    // and return which one it is
+    fn expect_scalar(&self, expr: &Expr, context: &str) -> Result<Type, String> {
+        match self.type_of(expr)? {
+            Type::Void => Err(format!("Type Error: {} has type 'void', but an 'int' or 'double' is required", context)),
+            ty => Ok(ty),
+        }
+    }
+
+    // Work out the type of an expression, checking its operands along the way
+    fn type_of(&self, expr: &Expr) -> Result<Type, String> {
+        match expr {
+            Expr::Constant(_) | Expr::Var(_) => Ok(Type::Int), // Every variable is an 'int'
+            Expr::FloatConstant(_) => Ok(Type::Double),
+            Expr::Unary(op, operand) => {
+                let context = format!("operand of '{}'", op.symbol());
+                match op {
+                    UnaryOp::Negate => self.expect_scalar(operand, &context),
+                    UnaryOp::Not => self.expect_scalar(operand, &context).map(|_| Type::Int),
+                    UnaryOp::Complement => self.expect_int(operand, &context).map(|_| Type::Int),
+                }
+            }
+            Expr::Binary(op, left, right) => {
+                let left_context = format!("left operand of '{}'", op.symbol());
+                let right_context = format!("right operand of '{}'", op.symbol());
+                match op {
+                    // Arithmetic converts an 'int' operand to 'double' when the other one is
+                    BinaryOp::Add | BinaryOp::Subtract | BinaryOp::Multiply | BinaryOp::Divide => {
+                        let left = self.expect_scalar(left, &left_context)?;
+                        let right = self.expect_scalar(right, &right_context)?;
+                        Ok(common_type(left, right))
+                    }
+                    BinaryOp::Less
+                    | BinaryOp::LessEqual
+                    | BinaryOp::Greater
+                    | BinaryOp::GreaterEqual
+                    | BinaryOp::Equal
+                    | BinaryOp::NotEqual
+                    | BinaryOp::And
+                    | BinaryOp::Or => {
+                        self.expect_scalar(left, &left_context)?;
+                        self.expect_scalar(right, &right_context)?;
+                        Ok(Type::Int)
+                    }
+                    // The remaining operators work on the bits of an integer
+                    _ => {
+                        self.expect_int(left, &left_context)?;
+                        self.expect_int(right, &right_context)?;
+                        Ok(Type::Int)
+                    }
+                }
+            }
+            Expr::PrefixInc(operand) | Expr::PrefixDec(operand) | Expr::PostfixInc(operand) | Expr::PostfixDec(operand) => {
+                self.expect_int(operand, "operand of '++' or '--'")?;
+                Ok(Type::Int)
+            }
+            Expr::SizeOf(operand) => match self.type_of(operand)? {
+                Type::Void => Err("Type Error: 'sizeof' applied to an expression of type 'void'".to_string()),
+                _ => Ok(Type::Int),
+            },
+            Expr::SizeOfType(ty) => match ty.size() {
+                Some(_) => Ok(Type::Int),
+                None => Err(format!("Type Error: 'sizeof' applied to type '{}', which has no size", ty)),
+            },
+            // Any value can be cast to 'void' to discard it; other casts convert between scalars
+            Expr::Cast(Type::Void, operand) => self.type_of(operand).map(|_| Type::Void),
+            Expr::Cast(ty, operand) => {
+                self.expect_scalar(operand, &format!("operand of cast to '{}'", ty))?;
+                Ok(ty.clone())
+            }
+            // The left operand's value is discarded, so it may be 'void'
+            Expr::Comma(left, right) => {
+                self.type_of(left)?;
+                self.type_of(right)
+            }
+            Expr::Assign(target, value) => {
+                self.expect_int(target, "target of '='")?;
+                self.expect_scalar(value, "assigned value")?;
+                Ok(Type::Int)
+            }
+            Expr::Conditional { cond, then, else_ } => {
+                self.expect_scalar(cond, "condition of '?:'")?;
+                // Both branches may be 'void', but one can't be 'void' while the other has a value
+                let then_type = self.type_of(then)?;
+                let else_type = self.type_of(else_)?;
+                if then_type != Type::Void && else_type != Type::Void {
+                    return Ok(common_type(then_type, else_type)); // An 'int' branch is converted like an arithmetic operand
+                }
+                if then_type != else_type {
+                    return Err(format!("Type Error: branches of '?:' have different types '{}' and '{}'", then_type, else_type));
+                }
+                Ok(then_type)
+            }
+            // Every value is an 'int' or 'void', so nothing can be subscripted yet
+            Expr::Subscript { base, .. } => {
+                Err(format!("Type Error: subscripted value has type '{}', but an array or pointer is required", self.type_of(base)?))
+            }
+            Expr::Deref(operand) => {
+                Err(format!("Type Error: operand of unary '*' has type '{}', but a pointer is required", self.type_of(operand)?))
+            }
+            Expr::AddrOf(_) => Err("Type Error: pointer types are not supported yet".to_string()),
+            Expr::Member { base, field } => Err(format!(
+                "Type Error: request for member '{}' in a value of type '{}', which is not a struct or union",
+                field,
+                self.type_of(base)?
+            )),
+            Expr::PtrMember { base, field } => Err(format!(
+                "Type Error: operand of '->{}' has type '{}', but a pointer to a struct or union is required",
+                field,
+                self.type_of(base)?
+            )),
+            Expr::Call { name, args } => {
+                for (i, arg) in args.iter().enumerate() {
+                    // Every parameter is an 'int', so a 'double' argument is converted to one
+                    self.expect_scalar(arg, &format!("argument {} of call to '{}'", i + 1, name))?;
+                }
+                // A function defined elsewhere is assumed to return 'int', like an implicit declaration
+                match self.functions.get(name) {
+                    Some(function) => {
+                        if !function.unspecified_params && function.params.len() != args.len() {
+                            return Err(format!(
+                                "Type Error: function '{}' takes {} argument(s), but {} were given",
+                                name,
+                                function.params.len(),
+                                args.len()
+                            ));
+                        }
+                        Ok(function.return_type.clone())
+                    }
+                    None => Ok(Type::Int),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+    use crate::semantics::resolve;
+
+    // Lex, parse, resolve, and type check a source
+    fn check_source(source: &str) -> Result<(), String> {
+        typecheck(&resolve(&parse(tokenize(source).unwrap()).unwrap())?)
+    }
+
+    #[test]
+    fn int_expressions_are_well_typed() {
+        assert!(check_source("int main(void) { int x = 1; return (x + 2) * 3 % 4 < 5 && !x; }").is_ok());
+    }
+
+    #[test]
+    fn void_call_cannot_be_used_in_arithmetic() {
+        let error = check_source("void f(void) { return; } int main(void) { return f() + 1; }").unwrap_err();
+        assert_eq!(error, "Type Error: left operand of '+' has type 'void', but an 'int' or 'double' is required");
+    }
+
+    #[test]
+    fn return_must_match_the_return_type() {
+        assert!(check_source("int main(void) { return 5; }").is_ok());
+        assert!(check_source("void f(void) { return; } int main(void) { f(); return 0; }").is_ok());
+        assert_eq!(
+            check_source("void f(void) { return 5; } int main(void) { return 0; }").unwrap_err(),
+            "Type Error: 'return' with a value in function 'f' returning 'void'"
+        );
+        assert_eq!(
+            check_source("int main(void) { return; }").unwrap_err(),
+            "Type Error: 'return' without a value in function 'main' returning 'int'"
+        );
+    }
+
+    #[test]
+    fn call_must_pass_one_argument_per_parameter() {
+        assert!(check_source("int add(int a, int b) { return a + b; } int main(void) { return add(1, 2); }").is_ok());
+        assert_eq!(
+            check_source("int add(int a, int b) { return a + b; } int main(void) { return add(1); }").unwrap_err(),
+            "Type Error: function 'add' takes 2 argument(s), but 1 were given"
+        );
+    }
+
+    #[test]
+    fn double_expressions_promote_and_convert() {
+        assert!(check_source("int main(void) { return (int)(1 + 2.5); }").is_ok());
+        assert!(check_source("double f(void) { return 1.0; } int main(void) { return 0; }").unwrap_err().contains("not supported yet"));
+    }
+}
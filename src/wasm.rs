@@ -0,0 +1,422 @@
+// WebAssembly backend: renders a checked AST as a WAT (text format) module, for running
+// compiled programs in a browser. Expressions map onto the wasm operand stack, and loops and
+// 'if' map onto wasm's structured blocks, so the AST is lowered directly instead of TACKY.
+use crate::ast::{BinaryOp, BlockItem, Declaration, Expr, ForInit, Function, Program, Statement, Type, UnaryOp}; // Import the AST node types
+use crate::typecheck::value_type; // Import the type of a checked expression, for 'sizeof'
+use std::collections::HashMap; // Import map type for the functions in the module
+
+// Render a whole program as a module with one exported function per C function
+pub fn gen(program: &Program) -> Result<String, String> {
+    let functions: HashMap<&str, &Function> = program.functions.iter().map(|function| (function.name.as_str(), function)).collect();
+    let mut out = String::from("(module\n");
+    for function in &program.functions {
+        out.push_str(&gen_function(function, &functions)?);
+    }
+    for function in &program.functions {
+        out.push_str(&format!("  (export \"{}\" (func ${}))\n", function.name, function.name));
+    }
+    out.push_str(")\n");
+    Ok(out)
+}
+
+// Render one function with its parameters and every local it declares
+fn gen_function(function: &Function, functions: &HashMap<&str, &Function>) -> Result<String, String> {
+    let mut header = format!("  (func ${}", function.name);
+    for (_, name) in &function.params {
+        header.push_str(&format!(" (param ${} i32)", name));
+    }
+    if function.return_type == Type::Int {
+        header.push_str(" (result i32)");
+    }
+    let mut gen = WatGen { out: header, depth: 2, counter: 0, loops: Vec::new(), functions };
+    let mut locals = Vec::new();
+    collect_locals(&function.body, &mut locals);
+    for name in locals {
+        gen.line(&format!("(local ${} i32)", name));
+    }
+    for item in &function.body {
+        gen.block_item(item)?;
+    }
+    // Falling off the end of an 'int' function returns 0; after a 'return' this is never
+    // reached, and wasm accepts unreachable code with any stack
+    if function.return_type == Type::Int {
+        gen.line("i32.const 0");
+    }
+    gen.out.push_str(")\n");
+    Ok(gen.out)
+}
+
+// List the unique name of every variable declared in a body, since wasm declares all of a
+// function's locals up front
+fn collect_locals(items: &[BlockItem], locals: &mut Vec<String>) {
+    for item in items {
+        match item {
+            BlockItem::Declaration(declaration) => locals.push(declaration.name.clone()),
+            BlockItem::Statement(statement) => collect_statement_locals(statement, locals),
+        }
+    }
+}
+
+// List the variables declared anywhere inside a statement
+fn collect_statement_locals(statement: &Statement, locals: &mut Vec<String>) {
+    match statement {
+        Statement::Block(items) => collect_locals(items, locals),
+        Statement::If { then, else_branch, .. } => {
+            collect_statement_locals(then, locals);
+            if let Some(else_branch) = else_branch {
+                collect_statement_locals(else_branch, locals);
+            }
+        }
+        Statement::For { init, body, .. } => {
+            if let Some(ForInit::Declaration(declaration)) = init {
+                locals.push(declaration.name.clone());
+            }
+            collect_statement_locals(body, locals);
+        }
+        Statement::While { body, .. }
+        | Statement::DoWhile { body, .. }
+        | Statement::Switch { body, .. }
+        | Statement::Label(_, body)
+        | Statement::Case(_, body)
+        | Statement::Default(body) => collect_statement_locals(body, locals),
+        _ => {}
+    }
+}
+
+// State for rendering the body of a single function
+struct WatGen<'a> {
+    out: String,                             // WAT text so far
+    depth: usize,                            // Indentation level of the next line
+    counter: usize,                          // Next number for block labels
+    loops: Vec<(String, String)>,            // Break and continue labels of the enclosing loops, innermost last
+    functions: &'a HashMap<&'a str, &'a Function>, // Every function in the module, to know what a call returns
+}
+
+impl WatGen<'_> {
+    // Append one instruction on its own line at the current depth
+    fn line(&mut self, instruction: &str) {
+        self.out.push('\n');
+        self.out.push_str(&"  ".repeat(self.depth));
+        self.out.push_str(instruction);
+    }
+
+    // Open a structured instruction like 'block $label', indenting what follows
+    fn open(&mut self, instruction: &str) {
+        self.line(instruction);
+        self.depth += 1;
+    }
+
+    // Close the innermost structured instruction
+    fn close(&mut self) {
+        self.depth -= 1;
+        self.line("end");
+    }
+
+    // Make a fresh block label with a readable prefix
+    fn make_label(&mut self, prefix: &str) -> String {
+        let label = format!("${}.{}", prefix, self.counter);
+        self.counter += 1;
+        label
+    }
+
+    // Render a declaration or statement
+    fn block_item(&mut self, item: &BlockItem) -> Result<(), String> {
+        match item {
+            BlockItem::Declaration(declaration) => self.declaration(declaration),
+            BlockItem::Statement(statement) => self.statement(statement),
+        }
+    }
+
+    // Render a declaration; only an initializer produces code
+    fn declaration(&mut self, declaration: &Declaration) -> Result<(), String> {
+        if let Some(init) = &declaration.init {
+            self.expr(init)?;
+            self.line(&format!("local.set ${}", declaration.name));
+        }
+        Ok(())
+    }
+
+    // Render an expression whose value is thrown away
+    fn discard(&mut self, expr: &Expr) -> Result<(), String> {
+        self.expr(expr)?;
+        if self.has_value(expr) {
+            self.line("drop");
+        }
+        Ok(())
+    }
+
+    // Render a statement
+    fn statement(&mut self, statement: &Statement) -> Result<(), String> {
+        match statement {
+            Statement::Return(expr) => {
+                if let Some(expr) = expr {
+                    self.expr(expr)?;
+                }
+                self.line("return");
+            }
+            Statement::Expression(expr) => self.discard(expr)?,
+            Statement::If { cond, then, else_branch } => {
+                self.expr(cond)?;
+                self.open("if"); // Takes the branch when the condition is nonzero
+                self.statement(then)?;
+                if let Some(else_branch) = else_branch {
+                    self.depth -= 1;
+                    self.line("else");
+                    self.depth += 1;
+                    self.statement(else_branch)?;
+                }
+                self.close();
+            }
+            Statement::While { cond, body } => self.gen_loop(Some(cond), None, body, false)?,
+            Statement::DoWhile { body, cond } => self.gen_loop(Some(cond), None, body, true)?,
+            Statement::For { init, cond, post, body } => {
+                match init {
+                    Some(ForInit::Declaration(declaration)) => self.declaration(declaration)?,
+                    Some(ForInit::Expression(expr)) => self.discard(expr)?,
+                    None => {}
+                }
+                self.gen_loop(cond.as_ref(), post.as_ref(), body, false)?;
+            }
+            Statement::Break | Statement::Continue => {
+                let Some((break_label, continue_label)) = self.loops.last() else {
+                    return Err("Codegen Error: 'break' or 'continue' outside of a loop".to_string());
+                };
+                let label = if matches!(statement, Statement::Break) { break_label } else { continue_label };
+                let instruction = format!("br {}", label);
+                self.line(&instruction);
+            }
+            Statement::Block(items) => {
+                for item in items {
+                    self.block_item(item)?;
+                }
+            }
+            // Without a 'goto' to reach it, a label is just the statement it marks
+            Statement::Label(_, statement) => self.statement(statement)?,
+            Statement::Goto(label) => {
+                return Err(format!("Codegen Error: 'goto {}' is not supported by the wasm backend yet", label));
+            }
+            Statement::Switch { .. } | Statement::Case(..) | Statement::Default(_) => {
+                return Err("Codegen Error: 'switch' statements are not supported yet".to_string());
+            }
+        }
+        Ok(())
+    }
+
+    // Render a loop as a 'loop' inside a 'block', so 'br' to the block breaks out and 'br' to
+    // the loop starts the next iteration. The body sits in its own block that 'continue'
+    // leaves, which runs the post expression and the test next.
+    fn gen_loop(&mut self, cond: Option<&Expr>, post: Option<&Expr>, body: &Statement, test_after: bool) -> Result<(), String> {
+        let break_label = self.make_label("break");
+        let top_label = self.make_label("loop");
+        let continue_label = self.make_label("continue");
+        self.open(&format!("block {}", break_label));
+        self.open(&format!("loop {}", top_label));
+        if let (Some(cond), false) = (cond, test_after) {
+            self.expr(cond)?;
+            self.line("i32.eqz");
+            self.line(&format!("br_if {}", break_label));
+        }
+        self.open(&format!("block {}", continue_label));
+        self.loops.push((break_label, continue_label));
+        self.statement(body)?;
+        self.loops.pop();
+        self.close();
+        if let Some(post) = post {
+            self.discard(post)?;
+        }
+        match (cond, test_after) {
+            (Some(cond), true) => {
+                self.expr(cond)?;
+                self.line(&format!("br_if {}", top_label)); // Loop again while the condition is nonzero
+            }
+            _ => self.line(&format!("br {}", top_label)),
+        }
+        self.close();
+        self.close();
+        Ok(())
+    }
+
+    // Whether an expression leaves a value on the stack; only a call to a 'void' function or a
+    // cast to 'void', possibly through '?:' or ',', leaves none
+    fn has_value(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Cast(Type::Void, _) => false,
+            Expr::Call { name, .. } => self.functions.get(name.as_str()).is_none_or(|function| function.return_type != Type::Void),
+            Expr::Conditional { then, .. } => self.has_value(then),
+            Expr::Comma(_, right) => self.has_value(right),
+            _ => true,
+        }
+    }
+
+    // Render an expression, leaving its value on the stack
+    fn expr(&mut self, expr: &Expr) -> Result<(), String> {
+        match expr {
+            Expr::Constant(value) => self.line(&format!("i32.const {}", value)),
+            Expr::Var(name) => self.line(&format!("local.get ${}", name)),
+            Expr::Unary(UnaryOp::Negate, operand) => {
+                self.line("i32.const 0"); // wasm has no integer negation, so this is '0 - operand'
+                self.expr(operand)?;
+                self.line("i32.sub");
+            }
+            Expr::Unary(UnaryOp::Complement, operand) => {
+                self.expr(operand)?;
+                self.line("i32.const -1");
+                self.line("i32.xor");
+            }
+            Expr::Unary(UnaryOp::Not, operand) => {
+                self.expr(operand)?;
+                self.line("i32.eqz");
+            }
+            // Short-circuit: the right operand only runs when the left one doesn't decide the result
+            Expr::Binary(BinaryOp::And, left, right) => {
+                self.expr(left)?;
+                self.open("if (result i32)");
+                self.expr(right)?;
+                self.line("i32.const 0");
+                self.line("i32.ne");
+                self.depth -= 1;
+                self.line("else");
+                self.depth += 1;
+                self.line("i32.const 0");
+                self.close();
+            }
+            Expr::Binary(BinaryOp::Or, left, right) => {
+                self.expr(left)?;
+                self.open("if (result i32)");
+                self.line("i32.const 1");
+                self.depth -= 1;
+                self.line("else");
+                self.depth += 1;
+                self.expr(right)?;
+                self.line("i32.const 0");
+                self.line("i32.ne");
+                self.close();
+            }
+            Expr::Binary(op, left, right) => {
+                self.expr(left)?;
+                self.expr(right)?;
+                self.line(binary_instruction(op));
+            }
+            Expr::Assign(target, value) => {
+                let name = var_name(target)?;
+                self.expr(value)?;
+                self.line(&format!("local.tee ${}", name)); // Store, keeping the value as the result
+            }
+            Expr::PrefixInc(operand) | Expr::PrefixDec(operand) => {
+                let name = var_name(operand)?;
+                self.line(&format!("local.get ${}", name));
+                self.line("i32.const 1");
+                self.line(if matches!(expr, Expr::PrefixInc(_)) { "i32.add" } else { "i32.sub" });
+                self.line(&format!("local.tee ${}", name));
+            }
+            Expr::PostfixInc(operand) | Expr::PostfixDec(operand) => {
+                let name = var_name(operand)?;
+                self.line(&format!("local.get ${}", name)); // The old value is the result
+                self.line(&format!("local.get ${}", name));
+                self.line("i32.const 1");
+                self.line(if matches!(expr, Expr::PostfixInc(_)) { "i32.add" } else { "i32.sub" });
+                self.line(&format!("local.set ${}", name));
+            }
+            Expr::Conditional { cond, then, else_ } => {
+                self.expr(cond)?;
+                self.open(if self.has_value(then) { "if (result i32)" } else { "if" });
+                self.expr(then)?;
+                self.depth -= 1;
+                self.line("else");
+                self.depth += 1;
+                self.expr(else_)?;
+                self.close();
+            }
+            Expr::Comma(left, right) => {
+                self.discard(left)?;
+                self.expr(right)?;
+            }
+            Expr::FloatConstant(_) | Expr::Cast(Type::Double, _) => {
+                return Err("Codegen Error: 'double' values are not supported by the wasm backend yet".to_string());
+            }
+            Expr::Cast(Type::Void, operand) => self.discard(operand)?,
+            Expr::Cast(_, operand) => self.expr(operand)?, // Without 'double' values, a cast to 'int' converts nothing
+            // The operand is never evaluated, and type checking has ruled out 'void'
+            Expr::SizeOf(operand) => self.line(&format!("i32.const {}", value_type(operand).size().unwrap())),
+            Expr::SizeOfType(ty) => match ty.size() {
+                Some(size) => self.line(&format!("i32.const {}", size)),
+                None => return Err(format!("Codegen Error: type '{}' has no size", ty)),
+            },
+            Expr::Call { name, args } => {
+                // A wasm module can only call functions it defines or imports, and nothing is imported
+                if !self.functions.contains_key(name.as_str()) {
+                    return Err(format!("Codegen Error: call to '{}', which is not defined in this file, is not supported by the wasm backend", name));
+                }
+                for arg in args {
+                    self.expr(arg)?;
+                }
+                self.line(&format!("call ${}", name));
+            }
+            Expr::Subscript { .. } => return Err("Codegen Error: array subscripts are not supported yet".to_string()),
+            Expr::Deref(_) | Expr::AddrOf(_) => return Err("Codegen Error: pointers are not supported yet".to_string()),
+            Expr::Member { .. } | Expr::PtrMember { .. } => {
+                return Err("Codegen Error: struct members are not supported yet".to_string())
+            }
+        }
+        Ok(())
+    }
+}
+
+// The unique name of a variable that is stored to; the parser only accepts lvalues, and
+// variables are the only ones supported so far
+fn var_name(expr: &Expr) -> Result<&str, String> {
+    match expr {
+        Expr::Var(name) => Ok(name),
+        _ => Err("Codegen Error: only variables can be assigned to yet".to_string()),
+    }
+}
+
+// The instruction that applies a binary operator to the two values on top of the stack.
+// Division, remainder, shifts, and comparisons use the signed forms, since every value is an 'int'.
+fn binary_instruction(op: &BinaryOp) -> &'static str {
+    match op {
+        BinaryOp::Add => "i32.add",
+        BinaryOp::Subtract => "i32.sub",
+        BinaryOp::Multiply => "i32.mul",
+        BinaryOp::Divide => "i32.div_s",
+        BinaryOp::Remainder => "i32.rem_s",
+        BinaryOp::ShiftLeft => "i32.shl",
+        BinaryOp::ShiftRight => "i32.shr_s",
+        BinaryOp::BitAnd => "i32.and",
+        BinaryOp::BitOr => "i32.or",
+        BinaryOp::BitXor => "i32.xor",
+        BinaryOp::Less => "i32.lt_s",
+        BinaryOp::LessEqual => "i32.le_s",
+        BinaryOp::Greater => "i32.gt_s",
+        BinaryOp::GreaterEqual => "i32.ge_s",
+        BinaryOp::Equal => "i32.eq",
+        BinaryOp::NotEqual => "i32.ne",
+        BinaryOp::And | BinaryOp::Or => unreachable!("'&&' and '||' are lowered to 'if'"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+    use crate::parser::parse;
+
+    // Lex, parse, and render a source as a WAT module
+    fn gen_source(source: &str) -> String {
+        gen(&parse(tokenize(source).unwrap()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn return_constant_is_an_exported_function() {
+        let wat = gen_source("int main(void) { return 42; }");
+        assert!(wat.starts_with("(module\n  (func $main (result i32)\n"));
+        assert!(wat.contains("i32.const 42\n"));
+        assert!(wat.contains("(export \"main\" (func $main))"));
+    }
+
+    #[test]
+    fn arithmetic_uses_the_operand_stack() {
+        let wat = gen_source("int main(void) { return 2 + 3 * 4; }");
+        assert!(wat.contains("i32.const 2\n    i32.const 3\n    i32.const 4\n    i32.mul\n    i32.add\n"));
+    }
+}
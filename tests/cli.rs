@@ -0,0 +1,341 @@
+// Command-line tests: run the built compiler binary on small programs and check what it
+// prints and the exit code it ends with
+use std::fs; // Import file writing for the test programs
+use std::io::Write; // Import writing to a child's standard input
+use std::path::PathBuf; // Import the path type for the test files
+use std::process::{Command, Output, Stdio}; // Import process spawning
+
+// The compiler binary Cargo built for these tests
+const COMPILER: &str = env!("CARGO_BIN_EXE_Compiler");
+
+// Write a file into a directory of its own for the test, so tests can run in parallel
+fn write_file(test: &str, name: &str, contents: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("compiler-cli-{}-{}", std::process::id(), test));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+// Run the compiler with the given arguments
+fn run(args: &[&str]) -> Output {
+    Command::new(COMPILER).args(args).output().unwrap()
+}
+
+// Run the compiler on one source file with the given arguments in front of it
+fn run_on(test: &str, source: &str, args: &[&str]) -> Output {
+    let path = write_file(test, "main.c", source);
+    let mut args = args.to_vec();
+    args.push(path.to_str().unwrap());
+    run(&args)
+}
+
+// Run the compiler with the given arguments, feeding `input` to its standard input
+fn run_with_stdin(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(COMPILER)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+// Text the compiler wrote to standard output and standard error
+fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+const RETURN_ZERO: &str = "int main(void) { return 0; }";
+
+#[test]
+fn reads_the_program_from_stdin() {
+    let output = run_with_stdin(&["--lex", "-"], "int main(void){return 0;}");
+    assert_eq!(output.status.code(), Some(0));
+    let out = stdout(&output);
+    assert!(out.contains("Performing lexical analysis on -"));
+    assert!(out.contains("1:1 IntKeyword"));
+    assert!(out.contains("ReturnKeyword"));
+}
+
+#[test]
+fn lex_prints_no_debug_output_by_default() {
+    let output = run_on("lex_quiet", RETURN_ZERO, &["--lex"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(!stderr(&output).contains("DEBUG:"), "{}", stderr(&output));
+}
+
+#[test]
+fn verbose_traces_each_token() {
+    let output = run_on("lex_verbose", RETURN_ZERO, &["--lex", "--verbose"]);
+    assert_eq!(output.status.code(), Some(0));
+    let err = stderr(&output);
+    assert!(err.contains("DEBUG: Found IntKeyword at line 1, column 1"), "{}", err);
+    assert!(err.contains("DEBUG: Found Identifier(\"main\") at line 1, column 5"), "{}", err);
+}
+
+#[test]
+fn every_input_file_is_lexed_in_order() {
+    let good = write_file("multiple_files", "good.c", RETURN_ZERO);
+    let bad = write_file("multiple_files", "bad.c", "int main(void) { return @; }");
+    let output = run(&["--lex", good.to_str().unwrap(), bad.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(4));
+    assert!(stdout(&output).contains(&format!("Performing lexical analysis on {}", good.display())));
+    assert!(stderr(&output).contains("Invalid character '@'"));
+}
+
+#[test]
+fn later_phases_read_stdin_too() {
+    let output = run_with_stdin(&["--parse", "-"], "int main(void){return 0;}");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("Return"));
+    let output = run_with_stdin(&["-s", "-"], "int main(void){return 3;}");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("movl $3, %eax"));
+}
+
+#[test]
+fn output_flag_chooses_the_assembly_path() {
+    let source = write_file("output_flag", "main.c", "int main(void) { return 42; }");
+    let assembly = source.with_file_name("custom.s");
+    let output = run(&["-s", source.to_str().unwrap(), "-o", assembly.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(fs::read_to_string(&assembly).unwrap().contains("movl $42, %eax"));
+    assert!(!source.with_extension("s").exists());
+}
+
+#[test]
+fn output_flag_needs_a_path() {
+    let path = write_file("output_flag_missing", "main.c", RETURN_ZERO);
+    let output = run(&["-s", path.to_str().unwrap(), "-o"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Missing output path after '-o'"));
+}
+
+#[test]
+fn version_works_without_a_path() {
+    for flag in ["--version", "-V"] {
+        let output = run(&[flag]);
+        assert_eq!(output.status.code(), Some(0));
+        assert!(stdout(&output).contains(env!("CARGO_PKG_VERSION")));
+    }
+}
+
+#[test]
+fn tokens_json_has_one_entry_per_token() {
+    let output = run_on("tokens_json", "int x;", &["--tokens-json"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(
+        stdout(&output).trim(),
+        r#"[{"kind":"IntKeyword","line":1,"col":1},{"kind":"Identifier","value":"x","line":1,"col":5},{"kind":"Semicolon","line":1,"col":6}]"#
+    );
+}
+
+#[test]
+fn parse_prints_the_tree() {
+    let output = run_on("parse_tree", "int main(void){return 2;}", &["--parse"]);
+    assert_eq!(output.status.code(), Some(0));
+    // Compare without the '{:#?}' indentation, which spreads 'Constant(2)' over three lines
+    let tree: String = stdout(&output).split_whitespace().collect();
+    assert!(tree.contains("Return(Some(Constant(2,),),)"), "{}", tree);
+}
+
+#[test]
+fn parse_errors_exit_with_the_parse_code() {
+    let output = run_on("parse_error", "int main(void) { return 2 }", &["--parse"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(stderr(&output).contains("Parse Error: expected ';'"));
+}
+
+#[test]
+fn format_reprints_the_program_as_c() {
+    let output = run_on("format", "int main(void){int x=(1+2)*3;if(x)return x;return 0;}", &["--format"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(
+        stdout(&output).ends_with("int main(void) {\n  int x = (1 + 2) * 3;\n  if (x)\n    return x;\n  return 0;\n}\n"),
+        "{}",
+        stdout(&output)
+    );
+}
+
+#[test]
+fn emit_stops_after_the_chosen_phase() {
+    let output = run_on("emit_ast", "int main(void) { return 2; }", &["--emit", "ast"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("Return"));
+    let output = run_on("emit_tacky", "int main(void) { return 1 + 2 * 3; }", &["--emit", "tacky"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("tmp.0 = 2 * 3"));
+}
+
+#[test]
+fn emit_may_follow_the_path() {
+    let path = write_file("emit_after_path", "main.c", RETURN_ZERO);
+    let output = run(&[path.to_str().unwrap(), "--emit", "tokens"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("IntKeyword"));
+}
+
+#[test]
+fn emit_rejects_unknown_phases_and_a_second_phase_option() {
+    let output = run_on("emit_unknown", RETURN_ZERO, &["--emit", "bytes"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("expected one of: tokens, ast, tacky, asm"));
+    let output = run_on("emit_conflict", RETURN_ZERO, &["--emit", "ast", "--lex"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Conflicting options '--emit ast' and '--lex'"));
+}
+
+#[test]
+fn response_file_arguments_are_expanded_in_place() {
+    let source = write_file("response_file", "main.c", RETURN_ZERO);
+    let response = write_file("response_file", "resp.txt", &format!("# lex the file\n--lex\n\n{}\n", source.display()));
+    let output = run(&[&format!("@{}", response.display())]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("IntKeyword"));
+}
+
+#[test]
+fn exit_codes_name_the_kind_of_failure() {
+    let output = run_on("exit_lex", "int main(void) { return @; }", &["--lex"]);
+    assert_eq!(output.status.code(), Some(4));
+    let missing = std::env::temp_dir().join("compiler-cli-no-such-file.c");
+    let output = run(&["--lex", missing.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(3));
+    assert!(stderr(&output).contains("Could not open file"));
+    let output = run(&["--lex"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn empty_file_lexes_to_nothing_and_fails_to_parse() {
+    let output = run_on("empty_lex", "", &["--lex"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout(&output), "");
+    let output = run_on("empty_parse", "", &["--parse"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(stderr(&output).contains("Parse Error: expected function definition, found end of file"));
+}
+
+// Three files where only the middle one fails to lex
+fn three_files(test: &str) -> [PathBuf; 3] {
+    [
+        write_file(test, "first.c", RETURN_ZERO),
+        write_file(test, "second.c", "int main(void) { return @; }"),
+        write_file(test, "third.c", RETURN_ZERO),
+    ]
+}
+
+#[test]
+fn keep_going_attempts_every_file() {
+    let files = three_files("keep_going");
+    let paths: Vec<&str> = files.iter().map(|path| path.to_str().unwrap()).collect();
+    let output = run(&["--lex", "--keep-going", paths[0], paths[1], paths[2]]);
+    assert_eq!(output.status.code(), Some(4));
+    let out = stdout(&output);
+    assert!(out.contains(&format!("Performing lexical analysis on {}", paths[0])));
+    assert!(out.contains(&format!("Performing lexical analysis on {}", paths[2])));
+}
+
+#[test]
+fn keep_going_names_each_failing_file() {
+    let bad1 = write_file("keep_going_failures", "bad1.c", "int main(void) { return @; }");
+    let ok = write_file("keep_going_failures", "ok.c", RETURN_ZERO);
+    let bad2 = write_file("keep_going_failures", "bad2.c", "int main(void) { return $; }");
+    let paths = [bad1.to_str().unwrap(), ok.to_str().unwrap(), bad2.to_str().unwrap()];
+    let output = run(&["--keep-going", "--lex", paths[0], paths[1], paths[2]]);
+    assert_eq!(output.status.code(), Some(4));
+    let banners: Vec<String> = stdout(&output).lines().filter(|line| line.starts_with("Performing")).map(String::from).collect();
+    let expected: Vec<String> = paths.iter().map(|path| format!("Performing lexical analysis on {}", path)).collect();
+    assert_eq!(banners, expected);
+    let err = stderr(&output);
+    assert!(err.contains("Invalid character '@'") && err.contains("Invalid character '$'"), "{}", err);
+}
+
+#[test]
+fn first_failure_stops_without_keep_going() {
+    let files = three_files("stop_at_failure");
+    let paths: Vec<&str> = files.iter().map(|path| path.to_str().unwrap()).collect();
+    let output = run(&["--lex", paths[0], paths[1], paths[2]]);
+    assert_eq!(output.status.code(), Some(4));
+    let out = stdout(&output);
+    assert!(out.contains(&format!("Performing lexical analysis on {}", paths[0])));
+    assert!(!out.contains(&format!("Performing lexical analysis on {}", paths[2])));
+}
+
+#[test]
+fn command_line_macros_are_substituted() {
+    for args in [["-DVALUE=7", "-s", "-"].as_slice(), ["-D", "VALUE=7", "-s", "-"].as_slice()] {
+        let output = run_with_stdin(args, "int main(void) { return VALUE; }");
+        assert_eq!(output.status.code(), Some(0));
+        assert!(stdout(&output).contains("movl $7, %eax"), "{}", stdout(&output));
+    }
+}
+
+#[test]
+fn tab_stop_flag_sets_the_reported_column() {
+    let output = run_on("tab_stop", "int main(void) {\n\treturn @;\n}\n", &["-ftabstop=4", "--lex"]);
+    assert_eq!(output.status.code(), Some(4));
+    assert!(stderr(&output).contains("Invalid character '@' at line 2, column 12"), "{}", stderr(&output));
+    let output = run_on("tab_stop_zero", RETURN_ZERO, &["-ftabstop=0", "--lex"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Invalid tab width '0' for '-ftabstop'"));
+}
+
+#[test]
+fn unknown_target_lists_the_valid_ones() {
+    let output = run_on("unknown_target", RETURN_ZERO, &["--target", "sparc", "-s"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("Unknown target 'sparc' for '--target'; expected one of: x86_64, arm64, wasm"));
+}
+
+#[test]
+fn target_selects_the_backend() {
+    let output = run_with_stdin(&["--target", "arm64", "-s", "-"], "int main(void) { return 7; }");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("mov w0, #7"));
+    let output = run_with_stdin(&["--target", "wasm", "-s", "-"], "int main(void) { return 42; }");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout(&output).contains("i32.const 42"));
+}
+
+#[test]
+fn run_exits_with_the_value_main_returns() {
+    let output = run_on("run_value", "int main(void){return 2+3;}", &["--run"]);
+    assert_eq!(output.status.code(), Some(5));
+}
+
+#[test]
+fn run_rejects_break_and_continue_outside_a_loop() {
+    let output = run_on("run_break", "int main(void){break;}", &["--run"]);
+    assert_eq!(output.status.code(), Some(7));
+    assert!(stderr(&output).contains("Runtime Error: 'break' outside of a loop in function 'main'"));
+    let output = run_on("run_continue", "int main(void){ { continue; } return 0; }", &["--run"]);
+    assert_eq!(output.status.code(), Some(7));
+    assert!(stderr(&output).contains("Runtime Error: 'continue' outside of a loop in function 'main'"));
+}
+
+#[test]
+fn run_exits_with_the_value_of_a_double_expression_cast_to_int() {
+    let output = run_on("run_double", "int main(void) { return (int)(3.0 / 2.0); }", &["--run"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn run_promotes_mixed_int_and_double_arithmetic() {
+    let output = run_on("run_promote", "int main(void) { return (int)(1 / 2 + 1 / 2.0 + 0.5); }", &["--run"]);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn double_variables_are_rejected_with_the_limitation_named() {
+    let output = run_on("double_variable", "int main(void) { double d = 1.5; return 0; }", &["--run"]);
+    assert_eq!(output.status.code(), Some(5));
+    assert!(stderr(&output).contains("variables of type 'double' are not supported yet"));
+}